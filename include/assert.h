@@ -0,0 +1,7 @@
+/* Minimal stub of <assert.h> that lang_c can parse. Assertions are left out, so they never end a path. */
+#ifndef _ASSERT_H
+#define _ASSERT_H
+
+#define assert(condition) ((void) 0)
+
+#endif
@@ -0,0 +1,23 @@
+/* Annotations for what C cannot say about ownership. They expand to attributes that compilers ignore, and that
+   c_borrow_checker reads in place of the ownership it would guess from the type.
+
+   OWNED         On a pointer: it owns what it points to, like a Box. On a return type: the result is new heap memory.
+                 On any other variable: it is an owner that is moved by assignment, even if it is not a struct.
+   BORROWED      On a pointer: a constant reference, even without const. On a return type: callers get one.
+   BORROWED_MUT  On a pointer: a mutable reference, even with const. On a return type: callers get one.
+   COPY          Copied instead of moved, even if it is a struct. On a return type: the result borrows no argument.
+   NULLABLE      On a pointer: it may point to nothing, so using it after it was set to NULL is not an error.
+                 On a return type: the pointer a caller assigns the result to may point to nothing.
+   LIFETIME(l)   Ties a returned pointer to the parameters with the same lifetime, like LIFETIME("'a").
+*/
+#ifndef _OWNERSHIP_H
+#define _OWNERSHIP_H
+
+#define OWNED __attribute__((annotate("owned")))
+#define BORROWED __attribute__((annotate("borrowed")))
+#define BORROWED_MUT __attribute__((annotate("borrowed_mut")))
+#define COPY __attribute__((annotate("copy")))
+#define NULLABLE __attribute__((annotate("nullable")))
+#define LIFETIME(lifetime) __attribute__((annotate(lifetime)))
+
+#endif
@@ -0,0 +1,9 @@
+/* Minimal stub of <stdbool.h> that lang_c can parse. */
+#ifndef _STDBOOL_H
+#define _STDBOOL_H
+
+#define bool _Bool
+#define true 1
+#define false 0
+
+#endif
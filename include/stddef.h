@@ -0,0 +1,12 @@
+/* Minimal stub of <stddef.h> that lang_c can parse. Only declares what the checker needs to know about. */
+#ifndef _STDDEF_H
+#define _STDDEF_H
+
+typedef unsigned long size_t;
+typedef long ptrdiff_t;
+typedef int wchar_t;
+
+#define NULL ((void *)0)
+#define offsetof(type, member) ((size_t) &((type *)0)->member)
+
+#endif
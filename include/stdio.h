@@ -0,0 +1,30 @@
+/* Minimal stub of <stdio.h> that lang_c can parse. FILE is left incomplete. */
+#ifndef _STDIO_H
+#define _STDIO_H
+
+#include <stddef.h>
+
+typedef struct _FILE FILE;
+
+extern FILE *stdin;
+extern FILE *stdout;
+extern FILE *stderr;
+
+#define EOF (-1)
+
+FILE *fopen(const char *path, const char *mode);
+int fclose(FILE *stream);
+int fflush(FILE *stream);
+int printf(const char *format, ...);
+int fprintf(FILE *stream, const char *format, ...);
+int sprintf(char *str, const char *format, ...);
+int snprintf(char *str, size_t size, const char *format, ...);
+int scanf(const char *format, ...);
+int puts(const char *str);
+int putchar(int c);
+int getchar(void);
+char *fgets(char *str, int size, FILE *stream);
+size_t fread(void *ptr, size_t size, size_t count, FILE *stream);
+size_t fwrite(const void *ptr, size_t size, size_t count, FILE *stream);
+
+#endif
@@ -0,0 +1,31 @@
+/* Minimal stub of <stdlib.h> that lang_c can parse. The heap functions are known to the checker by name. */
+#ifndef _STDLIB_H
+#define _STDLIB_H
+
+#include <stddef.h>
+
+#define EXIT_SUCCESS 0
+#define EXIT_FAILURE 1
+
+void *malloc(size_t size);
+void *calloc(size_t count, size_t size);
+void *realloc(void *ptr, size_t size);
+void *aligned_alloc(size_t alignment, size_t size);
+void free(void *ptr);
+
+_Noreturn void exit(int status);
+_Noreturn void _Exit(int status);
+_Noreturn void abort(void);
+
+int abs(int n);
+long labs(long n);
+int atoi(const char *str);
+long atol(const char *str);
+long strtol(const char *str, char **end, int base);
+unsigned long strtoul(const char *str, char **end, int base);
+char *getenv(const char *name);
+int rand(void);
+void srand(unsigned int seed);
+void qsort(void *base, size_t count, size_t size, int (*compare)(const void *, const void *));
+
+#endif
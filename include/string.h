@@ -0,0 +1,21 @@
+/* Minimal stub of <string.h> that lang_c can parse. */
+#ifndef _STRING_H
+#define _STRING_H
+
+#include <stddef.h>
+
+void *memcpy(void *dest, const void *src, size_t count);
+void *memmove(void *dest, const void *src, size_t count);
+void *memset(void *dest, int value, size_t count);
+int memcmp(const void *a, const void *b, size_t count);
+size_t strlen(const char *str);
+char *strcpy(char *dest, const char *src);
+char *strncpy(char *dest, const char *src, size_t count);
+char *strcat(char *dest, const char *src);
+int strcmp(const char *a, const char *b);
+int strncmp(const char *a, const char *b, size_t count);
+char *strchr(const char *str, int c);
+char *strstr(const char *str, const char *sub);
+char *strdup(const char *str);
+
+#endif
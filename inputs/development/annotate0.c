@@ -0,0 +1,64 @@
+// Ownership annotations from <ownership.h> override what the checker would guess from the types.
+
+#include <ownership.h>
+#include <stddef.h>
+
+struct Buffer {
+    int * OWNED data;
+    int * NULLABLE cursor;
+};
+
+struct Point {
+    int x;
+    int y;
+};
+
+void take(OWNED int handle);
+void show(const int * BORROWED_MUT p);
+void keep(struct Buffer b);
+OWNED int *create(int size);
+COPY int *config(int *key);
+int * NULLABLE lookup(int key);
+int * BORROWED peek(const int *p);
+int * BORROWED_MUT poke(const int *p);
+
+void main() {
+    // Copy structs are copied, not moved.
+    struct Point COPY p;
+    struct Point COPY q = p;
+    struct Point COPY r = p;            // fine, p was copied to q.
+
+    // Owned values are moved even if they are not structs.
+    OWNED int handle = 3;
+    take(handle);
+    take(handle);                       // ERROR: handle was moved into take.
+
+    // Pointers can be made mutable references even when they point to const.
+    int x = 1;
+    const int * BORROWED_MUT m = &x;
+    const int * BORROWED_MUT n = &x;    // invalidates m.
+    show(m);                            // ERROR: m is an invalid mutable reference to x.
+
+    // Nullable pointers can be set to NULL and still used.
+    struct Buffer b;
+    b.data = create(4);                 // b.data owns new heap memory.
+    b.cursor = NULL;
+    if (b.cursor == NULL) {
+        b.cursor = b.data;              // moves the owning pointer b.data to b.cursor.
+    }
+    keep(b);                            // ERROR: b is partially moved, since b.data was moved.
+
+    int *s = config(&x);                // s points to something global.
+    int *t = &x;
+    *s = 2;                             // fine, the result of config borrows nothing.
+
+    // Return types say what kind of reference callers get.
+    int *found = lookup(3);
+    *found = 4;                         // fine, the result of lookup may point to nothing.
+    int *u = peek(&x);
+    int *v = peek(&x);                  // fine, both are constant references to x.
+    int k = *u;
+    const int *w = poke(&x);
+    const int *z = poke(&x);            // invalidates w.
+    int j = *w;                         // ERROR: w is an invalid mutable reference to x.
+}
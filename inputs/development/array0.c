@@ -0,0 +1,65 @@
+#include <stdlib.h>
+
+struct foo {
+    int *data;
+};
+
+struct pair {
+    struct foo left;
+    int n;
+};
+
+struct bar {
+    struct foo items[2];
+    int count;
+};
+
+void use_foo(struct foo f);
+void read_all(const struct foo *items);
+
+void main() {
+    // Copy elements are copied out, but non-Copy ones cannot be moved out of the array.
+    int numbers[4];
+    int n = numbers[0];
+    struct foo arr[4];
+    struct foo taken = arr[1];           // ERROR: cannot move out of arr.
+    struct pair pairs[2];
+    int m = pairs[0].n;
+    struct foo left = pairs[1].left;     // ERROR: cannot move pairs.left out of an element.
+    use_foo(arr[3]);                     // ERROR: cannot move out of arr.
+
+    // Borrowing an element borrows the whole array.
+    struct foo *first = &arr[0];
+    struct foo *second = &arr[1];
+    (*first).data = NULL;                  // ERROR: first was invalidated by borrowing arr again.
+
+    // Passing an array to a function borrows it.
+    const struct foo *view = &arr[0];
+    read_all(arr);
+    int *data = (*view).data;
+    struct foo *p = arr;
+    (*p).data = NULL;
+
+    // Arrays inside structs are arrays too.
+    struct bar b;
+    struct foo inner = b.items[0];       // ERROR: cannot move out of b.items.
+    b.count = 1;
+
+    // Writing an element of an array of pointers does not use the pointers already in it.
+    int *ptrs[2];
+    int x;
+    ptrs[0] = &x;
+    *ptrs[0] = 1;
+
+    // An element of a multidimensional array is an array too, so it decays to a borrow of the whole array.
+    int grid[2][3];
+    int *row = grid[1];
+    *row = 2;
+    int *other_row = grid[0];
+    *row = 3;                            // ERROR: row was invalidated by borrowing grid again.
+}
+
+struct foo *dangling() {
+    struct foo local[2];
+    return local;                        // ERROR: local does not live past the function.
+}
@@ -0,0 +1,60 @@
+// The arrow operator, p->x, is the same as (*p).x.
+
+struct node {
+    int value;
+    struct node *next;
+};
+
+struct list {
+    struct node head;
+    int length;
+};
+
+struct holder {
+    struct list *list;
+};
+
+// Arrows chain through pointer parameters and the pointer members of struct parameters.
+int through_pointer(struct holder *h) {
+    return h->list->length;
+}
+
+int through_value(struct holder h) {
+    h.list->length = 2;
+    return h.list->head.value;
+}
+
+void main() {
+    // p->x checks that p is still a valid reference.
+    struct list l;
+    struct list *p = &l;
+    p->length = 1;
+    struct list *other = &l;
+    int n = p->length;                  // ERROR: p was invalidated by other.
+
+    // Owner fields cannot be moved out from behind a pointer.
+    struct list *q = &l;
+    struct node first = q->head;        // ERROR: cannot move q->head out from behind a reference.
+    int v = q->head.value;
+
+    // Chains follow every pointer.
+    struct node a;
+    struct node b;
+    a.next = &b;
+    struct node *r = &a;
+    r->next->value = 2;
+    struct node *c = &b;
+    r->next->value = 3;                 // ERROR: a.next was invalidated by c.
+
+    // A pointer that may point to several structs checks the field of each.
+    int flag = 1;
+    struct list m;
+    struct list k;
+    struct list *s = &m;
+    if (flag) {
+        s = &k;
+    }
+    struct node moved = k.head;
+    int w = s->head.value;              // ERROR: k.head was moved.
+
+}
@@ -1,4 +1,4 @@
-// Borrowing any part of a struct counts as borrowing the entire thing.
+// Borrowing any part of a struct conflicts with borrowing the entire thing.
 
 typedef struct Owner {
     int value;
@@ -0,0 +1,40 @@
+// Borrows of different fields of a struct do not conflict, but a field conflicts with the pieces above and below it.
+
+typedef struct Inner {
+    int a;
+    int b;
+} Inner;
+
+typedef struct Outer {
+    Inner inner;
+    int count;
+} Outer;
+
+void foo(int a);
+void bar(const Inner *i);
+
+void main() {
+    Outer s;
+    int *x = &s.count;
+    int *y = &s.inner.a;
+    int *z = &s.inner.b;                // borrows of s.count, s.inner.a and s.inner.b are disjoint.
+    foo(*x);
+    foo(*y);
+    foo(*z);
+
+    Inner *i = &s.inner;                // invalidates y and z, but not x.
+    foo(*x);
+    foo(*y);                            // ERROR: y was invalidated by borrowing s.inner.
+
+    int *w = &s.inner.a;                // invalidates i.
+    (*i).b = 1;                         // ERROR: i was invalidated by borrowing s.inner.a.
+
+    // Constant borrows of overlapping pieces do not conflict.
+    Outer t;
+    const Outer *ct = &t;
+    const int *cc = &t.count;
+    bar(&t.inner);
+    foo(*cc);
+    int *mc = &t.count;                 // invalidates ct and cc.
+    bar(&(*ct).inner);                  // ERROR: ct was invalidated by a mutable borrow of t.count.
+}
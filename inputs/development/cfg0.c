@@ -0,0 +1,23 @@
+// Function bodies are checked over a control flow graph, so plain blocks always run and ifs without an else might not.
+
+typedef struct Owner {
+    int value;
+} Owner;
+
+void foo(Owner a);
+
+void main() {
+    Owner x;
+    foo(x);                     // kills x.
+    {
+        Owner y;
+        x = y;                  // revives x. The block always runs.
+    }
+    foo(x);                     // kills x again.
+    Owner z;
+    x = z;                      // revives x.
+    if (1 > 0) {
+        foo(x);                 // kills x, only if the condition is true.
+    }
+    foo(x);                     // ERROR: x might have been moved inside the if.
+}
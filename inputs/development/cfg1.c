@@ -0,0 +1,21 @@
+// A pointer reassigned on only one branch may point to either target after the branches join.
+
+void foo(int a);
+
+void main(int flag) {
+    int a = 0;
+    int b = 0;
+    int *p = &a;
+    if (flag) {
+        p = &b;                 // p points to a or b after the if.
+    }
+    int *q = &b;                // invalidates p, if it points to b.
+    foo(*p);                    // ERROR: p may be an invalid reference to b.
+
+    int *m = &b;
+    if (flag) {
+        m = &a;
+    }
+    int *r = &a;                // invalidates m, if it points to a.
+    foo(*m);                    // ERROR: m may be an invalid reference to a.
+}
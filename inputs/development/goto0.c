@@ -0,0 +1,36 @@
+// Gotos carry their state to their label, like kernel-style error cleanup ladders.
+
+typedef struct Owner {
+    int value;
+} Owner;
+
+void foo(Owner a);
+void release(Owner a);
+
+int main(Owner a) {
+    Owner x;
+    Owner y;
+    if (a.value > 0) {
+        foo(x);                 // kills x.
+        goto err_x;             // skips straight to the cleanup.
+    }
+    if (a.value > 1) {
+        goto err_y;
+    }
+    return 0;
+
+err_y:
+    release(y);                 // kills y.
+err_x:
+    release(x);                 // ERROR: x was moved before the first goto.
+    return 1;
+}
+
+void retry(Owner a) {
+    Owner x;
+again:
+    foo(x);                     // ERROR: x was moved the previous time around.
+    if (a.value > 0) {
+        goto again;             // a backward goto is a loop.
+    }
+}
@@ -0,0 +1,26 @@
+// Real C library headers are replaced by the minimal ones in /include, which the parser can read.
+
+#include <stddef.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+struct Buffer {
+    char *data;
+    size_t length;
+};
+
+void main() {
+    char *text = malloc(16);
+    if (text == NULL) {
+        exit(EXIT_FAILURE);     // fine, exit never returns.
+    }
+    strcpy(text, "hello");
+    printf("%s\n", text);
+    free(text);
+    puts(text);                 // ERROR: text points to freed memory.
+
+    char *copy = calloc(4, 1);
+    copy = realloc(copy, 8);    // fine, realloc frees the old memory.
+    free(copy);
+}
@@ -0,0 +1,35 @@
+// Heap memory is owned by the pointer it is allocated to. Freeing it moves out of it.
+
+void *malloc(unsigned long size);
+void free(void *ptr);
+void use(int *p);
+
+int *make(int value) {
+    int *p = malloc(sizeof(int));
+    *p = value;
+    return p;                   // fine, the caller owns the memory now.
+}
+
+void main() {
+    int *p = malloc(sizeof(int));
+    *p = 1;
+    free(p);                    // frees the memory p points to.
+    use(p);                     // ERROR: p points to freed memory.
+    free(p);                    // ERROR: the memory was already freed.
+
+    int *q = malloc(sizeof(int));
+    q = malloc(sizeof(int));    // WARNING: the first allocation is leaked, nothing points to it anymore.
+    free(q);
+
+    int *r = (int *) malloc(sizeof(int));
+    if (*r > 0) {
+        free(r);
+        return;
+    }
+    free(r);
+
+    {
+        int *s = malloc(sizeof(int));
+        *s = 2;
+    }                           // WARNING: s goes out of scope without freeing its memory.
+}
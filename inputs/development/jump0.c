@@ -0,0 +1,37 @@
+// Paths that return, break, continue or call a noreturn function do not reach the code after them.
+
+typedef struct Owner {
+    int value;
+} Owner;
+
+void foo(Owner a);
+_Noreturn void fail(int code);
+
+int main(Owner a) {
+    Owner x;
+    if (a.value > 0) {
+        foo(x);                 // kills x.
+        return 1;               // the function ends here.
+    }
+    foo(x);                     // fine, x was only moved on a path that returned.
+
+    Owner y;
+    while (a.value > 1) {
+        if (a.value > 2) {
+            continue;           // goes back to the condition, y is still valid.
+        }
+        if (a.value > 3) {
+            foo(y);             // kills y.
+            break;              // leaves the loop with y moved.
+        }
+    }
+    foo(y);                     // ERROR: y might have been moved before breaking out of the loop.
+
+    Owner z;
+    if (a.value > 4) {
+        foo(z);                 // kills z.
+        fail(1);                // never returns.
+    }
+    foo(z);                     // fine, z was only moved on a path that never returns.
+    return 0;
+}
@@ -0,0 +1,41 @@
+// Results of functions borrow from their arguments, by Rust's elision rule or by explicit lifetimes.
+
+#define LIFETIME(name) __attribute__((annotate(name)))
+
+// One pointer parameter: the result borrows from it.
+int *first(int *array);
+
+// Two pointer parameters: without lifetimes, the result is not tied to either.
+int *either(int *a, int *b);
+
+// The result borrows from a only.
+int * LIFETIME("'a") pick(int * LIFETIME("'a") a, int *b);
+
+// The result borrows from nothing the caller passed in.
+int * LIFETIME("'static") lookup(int *key);
+
+int * LIFETIME("'a") longest(int * LIFETIME("'a") a, int * LIFETIME("'b") b) {
+    if (*a > *b) {
+        return a;
+    }
+    return b;                   // ERROR: b does not have the lifetime 'a.
+}
+
+void main() {
+    int x = 1;
+    int y = 2;
+
+    int *p = first(&x);         // p borrows x.
+    int *m = &x;                // invalidates p.
+    *p = 3;                     // ERROR: p is an invalid reference to x.
+
+    int *q = pick(&x, &y);      // q borrows x, not y.
+    int *n = &y;
+    *q = 4;                     // fine, y was borrowed again but q only borrows x.
+    m = &x;
+    *q = 5;                     // ERROR: q is an invalid reference to x.
+
+    int *r = lookup(&x);
+    m = &x;
+    *r = 6;                     // fine, r does not borrow x.
+}
@@ -0,0 +1,29 @@
+// Loops run until their state stops changing, so a move in one iteration is seen by the next.
+
+typedef struct Owner {
+    int value;
+} Owner;
+
+void foo(Owner a);
+
+void main() {
+    Owner x;
+    int i = 0;
+    while (i < 10) {
+        foo(x);                 // ERROR: x was moved by the previous iteration.
+        i = i + 1;
+    }
+
+    Owner y;
+    do {
+        Owner z = y;            // ERROR: y was moved by the previous iteration.
+    } while (i > 0);
+
+    Owner w;
+    for (int j = 0; j < 10; j = j + 1) {
+        foo(w);                 // kills w.
+        Owner v;
+        w = v;                  // revives w, so the next iteration is fine.
+    }
+    foo(w);
+}
@@ -0,0 +1,14 @@
+// A reference to a variable declared inside a loop body dangles in the next iteration.
+
+void foo(int a);
+
+void main() {
+    int initial = 5;
+    const int *ref = &initial;
+    for (int i = 0; i < 10; i = i + 1) {
+        foo(*ref);              // ERROR: ref points to x from the previous iteration.
+        int x = i;
+        ref = &x;
+    }
+    int i = 0;                  // the loop's i is only in scope inside the for statement.
+}
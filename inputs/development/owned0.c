@@ -0,0 +1,30 @@
+// Owning pointers are like Box: assigning one moves it, and using it borrows what it points to.
+
+void *malloc(unsigned long size);
+void free(void *ptr);
+void use(int *p);
+void consume(int * __attribute__((annotate("owned"))) p);
+
+void main() {
+    int *p = malloc(sizeof(int));   // p owns the new memory.
+    int *q = p;                     // moves ownership from p to q.
+    *p = 1;                         // ERROR: p was moved to q.
+    use(q);                         // fine, use only borrows q.
+    *q = 2;
+    consume(q);                     // moves q into consume, which owns its parameter.
+    *q = 3;                         // ERROR: q was moved into consume.
+
+    int * __attribute__((annotate("owned"))) r = malloc(sizeof(int));
+    if (*r > 0) {
+        consume(r);
+    }
+    use(r);                         // ERROR: r might have been moved inside the if.
+
+    // A pointer that only owns its memory on one path still owns it after the paths join.
+    int x;
+    int *s = &x;
+    if (x > 0) {
+        s = malloc(sizeof(int));
+    }
+    free(s);                        // fine, the memory is not leaked.
+}
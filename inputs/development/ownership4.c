@@ -0,0 +1,36 @@
+// Moving a field out of a struct leaves it partially moved.
+
+struct Inner {
+    int value;
+};
+
+struct Outer {
+    struct Inner first;
+    struct Inner second;
+    int count;
+};
+
+void take(struct Outer o);
+void take_inner(struct Inner i);
+void look(const struct Outer *o);
+
+void main() {
+    struct Outer a;
+    struct Inner i = a.first;
+    a.count = 1;                        // the other fields can still be used.
+    take_inner(a.second);
+    take(a);                            // ERROR: a.first and a.second were moved.
+
+    // Reassigning the moved field makes the struct whole again.
+    struct Outer b;
+    struct Inner j = b.first;
+    look(&b);                           // ERROR: b.first was moved.
+    b.first = j;
+    look(&b);
+    take(b);
+
+    // Moving a partially moved struct is one error, not one for each moved field.
+    struct Outer c;
+    struct Inner k = c.first;
+    struct Outer d = c;                 // ERROR: c.first was moved.
+}
@@ -0,0 +1,40 @@
+#include <stdlib.h>
+
+void main() {
+    // A pointer computed from another one borrows the same memory.
+    int x = 0;
+    int *p = &x;
+    int *q = p + 1;
+    int *r = &p[2];
+    *q = 1;
+    *p = 2;                             // p is still valid, like after a reborrow.
+    int *m = &x;                        // invalidates p, q and r.
+    *q = 3;                             // ERROR: q was invalidated.
+    *(r - 1) = 4;                       // ERROR: r was invalidated.
+
+    // Stepping a pointer through memory keeps its provenance.
+    int y = 0;
+    int *s = &y;
+    s = s + 1;
+    s++;
+    *s = 5;
+
+    // Arithmetic on an array borrows the whole array.
+    int arr[8];
+    int *a = arr + 4;
+    int *b = &arr[0];
+    *a = 6;                             // ERROR: a was invalidated by borrowing arr again.
+
+    // Derived pointers into freed memory are dangling.
+    char *buffer = malloc(16);
+    char *end = buffer + 16;
+    free(buffer);
+    *(end - 1) = 0;                     // ERROR: end points into freed memory.
+
+    // Constant references can only derive constant references.
+    int z = 0;
+    const int *c = &z;
+    const int *d = c + 1;
+    int *e = c + 1;                     // ERROR: e is mutable but c is not.
+    int w = *(d - 1);
+}
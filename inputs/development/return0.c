@@ -0,0 +1,54 @@
+// Returned values must outlive the function, and returning an owner moves it to the caller.
+
+typedef struct Owner {
+    int value;
+} Owner;
+
+int global;
+
+int *local_address() {
+    int x = 1;
+    return &x;                  // ERROR: x is dropped when the function returns.
+}
+
+int *local_pointer(int n) {
+    int x = n;
+    int *p = &x;
+    return p;                   // ERROR: p points to x, which is dropped when the function returns.
+}
+
+int *parameter_address(int n) {
+    return &n;                  // ERROR: parameters are local copies.
+}
+
+int *global_address() {
+    return &global;             // fine, globals outlive every function.
+}
+
+int *static_address() {
+    static int counter;
+    return &counter;            // fine, static locals outlive every function.
+}
+
+int *parameter_pointer(int *p) {
+    return p;                   // fine, what p points to belongs to the caller.
+}
+
+int *member_address(Owner *o) {
+    return &(*o).value;         // fine, *o belongs to the caller.
+}
+
+Owner pass(Owner a) {
+    return a;                   // moves a to the caller.
+}
+
+Owner twice(Owner a) {
+    Owner b = a;
+    return a;                   // ERROR: a was moved to b.
+}
+
+void main() {
+    Owner a;
+    Owner b = pass(a);
+    pass(a);                    // ERROR: pass consumes a, because it returns it.
+}
@@ -0,0 +1,50 @@
+// Run with --spec inputs/development/spec0.toml. The C library functions are known from the bundled specs/libc.toml.
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+struct queue {
+    int *head;
+    char *label;
+};
+
+void queue_push(struct queue *q, int *item);
+int *queue_peek(struct queue *q);
+char *queue_name(struct queue *q, int *index);
+void queue_destroy(struct queue *q);
+
+void main() {
+    // fopen returns memory that fclose frees.
+    FILE *file = fopen("input.txt", "r");
+    fclose(file);
+    fclose(file);                       // ERROR: file was already closed.
+
+    // The result of strchr borrows from its first argument.
+    char *text = strdup("a,b");
+    char *comma = strchr(text, ',');
+    free(text);
+    *comma = ';';                       // ERROR: comma points into freed memory.
+
+    // The spec makes the item parameter of queue_push owning, so the item is moved.
+    struct queue q;
+    int *item = malloc(4);
+    queue_push(&q, item);
+    *item = 1;                          // ERROR: item was moved into queue_push.
+
+    // The result of queue_peek borrows from q, and queue_name's borrows nothing.
+    int x = 0;
+    int *top = queue_peek(&q);
+    char *name = queue_name(&q, &x);
+    queue_push(&q, malloc(4));          // invalidates top, but not name.
+    *name = 'q';
+    *top = 2;                           // ERROR: top is an invalid reference to q.
+
+    // The head field owns what it points to, and the label may point to nothing.
+    q.label = NULL;
+    q.head = malloc(4);
+    int *head = q.head;                 // moves q.head.
+    *q.head = 3;                        // ERROR: q.head was moved.
+    queue_destroy(&q);                  // ERROR: q is partially moved.
+    free(head);
+}
@@ -0,0 +1,83 @@
+// Calls to functions defined in the file use what their bodies do with each argument.
+
+typedef struct Owner {
+    int x;
+} Owner;
+
+void free(void *ptr);
+void *malloc(unsigned long size);
+void consume(Owner a);
+
+// Only reads its argument, so the caller keeps ownership.
+int peek(Owner a) {
+    return a.x;
+}
+
+// Moves its argument into a function that consumes it.
+void forward(Owner a) {
+    consume(a);
+}
+
+// Frees what its argument points to, so the caller loses it.
+void destroy(int *p) {
+    free(p);
+}
+
+// The returned pointer borrows from the argument.
+int *identity(int *p) {
+    return p;
+}
+
+// New heap memory that the caller owns.
+int *make() {
+    int *p = malloc(sizeof(int));
+    return p;
+}
+
+// Recursive: consumes its argument on one path, found by repeating until the summaries stop changing.
+void countdown(Owner a, int n) {
+    if (n > 0) {
+        countdown(a, n - 1);
+    } else {
+        consume(a);
+    }
+}
+
+// The checker cannot follow this body (n is not a struct), so it gets no summary and calls to it move their owners.
+int confusing(Owner a, int n) {
+    return n.x;
+}
+
+// Never called by main, so it is not summarized when only main is checked.
+void unrelated(int n) {
+    int m = n.x;
+}
+
+void main() {
+    Owner a;
+    peek(a);
+    peek(a);                    // fine, peek only borrows a.
+    forward(a);
+    peek(a);                    // ERROR: a was moved into forward.
+
+    Owner b;
+    countdown(b, 3);
+    peek(b);                    // ERROR: b was moved into countdown.
+
+    Owner c;
+    confusing(c, 1);
+    peek(c);                    // ERROR: c was moved into confusing.
+
+    int *p = make();            // p owns new heap memory.
+    destroy(p);                 // fine, destroy frees it.
+
+    int x = 1;
+    int y = 2;
+    int *q = identity(&x);      // q borrows x.
+    int *r = &x;                // the new mutable borrow of x invalidates q.
+    *q = 3;                     // ERROR: q is an invalid reference to x.
+    q = identity(&y);
+    *q = 4;                     // fine, q now borrows y.
+
+    int *s = make();
+}                               // WARNING: the memory s points to is never freed.
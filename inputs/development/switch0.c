@@ -0,0 +1,44 @@
+// Each case of a switch starts from the state before the switch, plus whatever falls through from the case above it.
+
+typedef struct Owner {
+    int value;
+} Owner;
+
+void foo(Owner a);
+
+void main(Owner a) {
+    Owner x;
+    Owner y;
+    switch (a.value) {
+        case 0:
+            foo(x);             // kills x.
+            break;
+        case 1:
+            foo(x);             // fine, case 0 broke out before reaching this case.
+        case 2:
+            foo(x);             // ERROR: x was moved by case 1 falling through.
+            break;
+        default:
+            foo(y);             // kills y.
+    }
+    foo(x);                     // ERROR: x was moved in cases 0, 1 and 2.
+    foo(y);                     // ERROR: y was moved in the default case.
+
+    Owner z;
+    switch (a.value) {
+        case 3:
+            foo(z);             // kills z.
+            break;
+    }
+    foo(z);                     // ERROR: z was moved in case 3.
+
+    Owner w;
+    switch (a.value) {
+        case 4:
+            foo(w);             // kills w.
+            return;
+        default:
+            break;
+    }
+    foo(w);                     // fine, case 4 returned.
+}
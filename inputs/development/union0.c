@@ -0,0 +1,56 @@
+// Enums are Copy, and the members of a union share their memory.
+
+enum color { RED, GREEN, BLUE };
+
+typedef enum { SMALL, LARGE } size;
+
+struct foo {
+    int *data;
+};
+
+struct shape {
+    enum { CIRCLE, SQUARE } kind;
+    int sides;
+};
+
+union value {
+    int number;
+    int *pointer;
+    struct foo owner;
+};
+
+void paint(enum color c);
+void take(struct foo f);
+
+void main() {
+    // Enum values are copied, not moved.
+    enum color c = RED;
+    enum color d = c;
+    paint(c);
+    paint(c);
+    size s = LARGE;
+    size t = s;
+
+    // Enums declared inside a member declare their constants too.
+    struct shape sh;
+    sh.kind = SQUARE;
+    int k = sh.kind;
+
+    // Writing one member of a union invalidates borrows of the others.
+    union value v;
+    int *n = &v.number;
+    int x = 0;
+    v.pointer = &x;
+    *n = 1;                             // ERROR: v.number was overwritten by v.pointer.
+    int **p = &v.pointer;
+    v.number = 2;
+    int *q = &v.number;
+    *q = 3;
+
+    // Reading an owner out of a union may reinterpret another member (a warning).
+    union value w;
+    w.number = 4;
+    struct foo f = w.owner;             // WARNING: w.owner may really be w.number.
+    union value u;
+    take(u.owner);                      // WARNING: u.owner may really be another member.
+}
@@ -0,0 +1,23 @@
+// Calls functions that were never declared. Run with --unknown-functions permissive or error to compare.
+
+struct foo {
+    int *data;
+};
+
+void main() {
+    // By default, an owner passed to an unknown function is moved.
+    struct foo a;
+    consume(a);
+    struct foo b = a;                   // ERROR: a was moved into consume.
+
+    // And a pointer passed to one is borrowed mutably.
+    int x = 0;
+    const int *r = &x;
+    modify(&x);
+    int y = *r;                         // ERROR: r was invalidated by modify.
+
+    // Arguments are checked for moved values even when the function is unknown.
+    struct foo c;
+    struct foo d = c;
+    inspect(c);                         // ERROR: c was moved into d.
+}
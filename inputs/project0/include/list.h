@@ -0,0 +1,12 @@
+// Included through the -I flag in compile_commands.json.
+#ifndef LIST_H
+#define LIST_H
+
+#include <stdlib.h>
+
+struct List {
+    int *items;
+    size_t length;
+};
+
+#endif
@@ -0,0 +1,5 @@
+// Cannot be parsed. The other files are still checked.
+
+void main() {
+    int x = ;
+}
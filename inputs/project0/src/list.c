@@ -0,0 +1,12 @@
+// Checked with the flags from compile_commands.json: -I include -DCAPACITY=8.
+
+#include "list.h"
+
+void list_use(struct List *list);
+
+void main() {
+    struct List list;
+    list.items = malloc(CAPACITY * sizeof(int));
+    free(list.items);
+    list.items[0] = 1;          // ERROR: list.items points to freed memory.
+}
@@ -0,0 +1,15 @@
+// Checked with the flags from compile_commands.json. DEBUG is only defined for this file.
+
+#include "list.h"
+
+struct Owner {
+    int value;
+};
+
+void main() {
+    struct Owner a;
+    struct Owner b = a;
+#ifdef DEBUG
+    struct Owner c = a;         // ERROR: a was moved to b.
+#endif
+}
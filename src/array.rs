@@ -0,0 +1,187 @@
+use crate::diagnostic::*;
+use crate::variable::*;
+use crate::BorrowChecker;
+use lang_c::ast::*;
+use lang_c::span::*;
+use lang_c::visit::Visit;
+
+// Functions for arrays. An array is one variable for all of its elements, so arr[i] is arr and arr[i].x is arr.x.
+// Borrowing an element borrows the whole array, and a non-Copy element cannot be moved out, like in Rust.
+impl<'a> BorrowChecker<'a> {
+    // Whether a declarator declares an array, like int *a[4] (an array of pointers).
+    pub fn is_array_declarator(declarator: &Declarator) -> bool {
+        declarator
+            .derived
+            .last()
+            .is_some_and(|d| matches!(d.node, DerivedDeclarator::Array(_)))
+    }
+
+    // The number of dimensions an array declarator has, like 2 for int m[2][3].
+    pub fn array_dimensions(declarator: &Declarator) -> usize {
+        declarator
+            .derived
+            .iter()
+            .rev()
+            .take_while(|d| matches!(d.node, DerivedDeclarator::Array(_)))
+            .count()
+    }
+
+    // Whether an element of a multidimensional array is itself an array, like m[i] of int m[2][3].
+    pub fn is_sub_array(&mut self, expression: &Node<Expression>) -> bool {
+        let mut indices = 0;
+        let mut array = expression;
+        while let Expression::BinaryOperator(boe) = &array.node {
+            if boe.node.operator.node != BinaryOperator::Index {
+                return false;
+            }
+            indices += 1;
+            array = &boe.node.lhs;
+        }
+        let Expression::Identifier(id) = &array.node else {
+            return false;
+        };
+        indices > 0 && indices < self.name_to_var(&id.node.name).dimensions
+    }
+
+    pub fn is_array(&mut self, name: &str) -> bool {
+        name != "NULL" && self.name_to_var(name).array
+    }
+
+    // The array an element or a member of an element belongs to: arr for arr[i] and arr[i].x.
+    pub fn indexed_array(expression: &Node<Expression>) -> Option<&Node<Expression>> {
+        match &expression.node {
+            Expression::BinaryOperator(boe) if boe.node.operator.node == BinaryOperator::Index => {
+                Some(&boe.node.lhs)
+            }
+            Expression::Member(member_expression)
+                if member_expression.node.operator.node == MemberOperator::Direct =>
+            {
+                Self::indexed_array(&member_expression.node.expression)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_indexed(expression: &Node<Expression>) -> bool {
+        Self::indexed_array(expression).is_some()
+    }
+
+    // Whether an expression is a variable or a piece of one, like x, s.y or arr[i], and not something reached through
+    // a pointer.
+    pub fn is_place(expression: &Node<Expression>) -> bool {
+        match &expression.node {
+            Expression::Identifier(_) => true,
+            Expression::Member(member_expression) => {
+                member_expression.node.operator.node == MemberOperator::Direct
+                    && Self::is_place(&member_expression.node.expression)
+            }
+            Expression::BinaryOperator(boe) => {
+                boe.node.operator.node == BinaryOperator::Index && Self::is_place(&boe.node.lhs)
+            }
+            _ => false,
+        }
+    }
+
+    // The variable that passing an array to a function borrows, since it decays to a pointer to its first element.
+    pub fn decayed_array(&mut self, expression: &Node<Expression>) -> Option<Id> {
+        if self.is_sub_array(expression) {
+            self.visit_indices(expression);
+            return self.borrowed_variable(expression);
+        }
+        if Self::is_indexed(expression) {
+            return None;
+        }
+        match &expression.node {
+            Expression::Identifier(_) | Expression::Member(_) => {
+                let name = self.expression_name(expression)?;
+                if self.is_array(&name) {
+                    self.borrowed_variable(expression)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // arr[i]: the array is visited as part of the expression, and the index on its own, so that in arr[i].x the
+    // index is not part of the member name.
+    pub fn visit_index(&mut self, boe: &BinaryOperatorExpression) {
+        self.visit_expression(&boe.lhs.node, &boe.lhs.span);
+        self.visit_index_only(&boe.rhs);
+    }
+
+    // The index of arr[i] on its own, outside of any member name being built.
+    fn visit_index_only(&mut self, index: &Node<Expression>) {
+        let member_identifier_pieces = std::mem::take(&mut self.member_identifier_pieces);
+        let member_identifier = std::mem::take(&mut self.member_identifier);
+        let member_count = std::mem::replace(&mut self.member_count, 0);
+        let mute_member_expression = std::mem::replace(&mut self.mute_member_expression, false);
+        self.visit_expression(&index.node, &index.span);
+        self.member_identifier_pieces = member_identifier_pieces;
+        self.member_identifier = member_identifier;
+        self.member_count = member_count;
+        self.mute_member_expression = mute_member_expression;
+    }
+
+    // The indices of an element or a member of an element, like i in arr[i].x and i and j in m[i][j].
+    fn visit_indices(&mut self, expression: &Node<Expression>) {
+        match &expression.node {
+            Expression::BinaryOperator(boe) if boe.node.operator.node == BinaryOperator::Index => {
+                self.visit_indices(&boe.node.lhs);
+                self.visit_index_only(&boe.node.rhs);
+            }
+            Expression::Member(member_expression)
+                if member_expression.node.operator.node == MemberOperator::Direct =>
+            {
+                self.visit_indices(&member_expression.node.expression);
+            }
+            _ => {}
+        }
+    }
+
+    // arr[i] = ... or arr[i].y = ...: writing an element only uses the index, not the array (whose elements may
+    // still be uninitialized pointers), but the array must not have been moved. The assignment then updates what
+    // the array points to. Returns false for p[i] = ..., which writes through the pointer p and uses it.
+    pub fn write_to_element(&mut self, expression: &Node<Expression>) -> bool {
+        let Some(array) = Self::indexed_array(expression).and_then(|a| self.expression_name(a))
+        else {
+            return false;
+        };
+        if !self.is_array(&array) {
+            return false;
+        }
+        self.visit_indices(expression);
+        self.announce_no_ownership(array, &expression.span);
+        true
+    }
+
+    // x = arr[i] or x = arr[i].y: Copy values are copied out of the element, but non-Copy ones cannot be moved out,
+    // because the checker (like Rust) cannot tell which element was left without a value. If index moves are
+    // allowed, the whole array is moved instead.
+    pub fn move_out_of_index(&mut self, expression: &Node<Expression>, span: &Span) {
+        let Some(name) = self.expression_name(expression) else {
+            return;
+        };
+        if !matches!(
+            self.name_to_var(&name).var_type,
+            VarType::Owner(_, _) | VarType::OwnedPtr(_, _)
+        ) {
+            return;
+        }
+        if self.allow_index_moves {
+            self.set_all_ownership(name, false, span);
+            return;
+        }
+
+        let array = Self::indexed_array(expression)
+            .and_then(|array| self.expression_name(array))
+            .unwrap_or_default();
+        let message = if name == array {
+            format!("Cannot move a non-Copy element out of array '{array}'")
+        } else {
+            format!("Cannot move '{name}' out of an element of array '{array}'")
+        };
+        self.report(ErrorCode::MoveOutOfArray, span, message, vec![], vec![name]);
+    }
+}
@@ -1,12 +1,18 @@
+use crate::summary::ParameterMode;
 use crate::variable::*;
 use crate::BorrowChecker;
-use crate::PrintType;
 use lang_c::ast::*;
 use lang_c::loc;
 use lang_c::*;
 use std::collections::HashMap;
 
 impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
+    // Summarizes every function definition before any of them are checked, so calls can use the summaries.
+    fn visit_translation_unit(&mut self, translation_unit: &'ast TranslationUnit) {
+        self.summarize_functions(translation_unit);
+        visit::visit_translation_unit(self, translation_unit);
+    }
+
     // For things declared at the global scope (function prototypes, struct definitions, global variables).
     fn visit_external_declaration(
         &mut self,
@@ -23,6 +29,7 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
             }
             ExternalDeclaration::Declaration(declaration) => {
                 let mut no_visit = false;
+                self.declare_enumerators(&declaration.node.specifiers);
                 // For struct definitions, which we use to know the types of undeclared struct members.
                 for specifier in &declaration.node.specifiers {
                     if let DeclarationSpecifier::TypeSpecifier(type_specifier) = &specifier.node {
@@ -53,6 +60,11 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
                             self.add_function(
                                 &init_declarator.node.declarator,
                                 &function_declarator.node.parameters,
+                                &declaration.node.specifiers,
+                            );
+                            self.add_if_noreturn(
+                                &init_declarator.node.declarator,
+                                &declaration.node.specifiers,
                             );
                             return;
                         }
@@ -73,43 +85,29 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
         function_definition: &'ast FunctionDefinition,
         _: &'ast span::Span,
     ) {
+        self.add_if_noreturn(
+            &function_definition.declarator,
+            &function_definition.specifiers,
+        );
         if let DeclaratorKind::Identifier(id) = &function_definition.declarator.node.kind.node {
             // Ignore any function definitions that the user did not specify to be checked.
-            if self.functions_to_check.contains(&id.node.name) {
-                // Functions add the new scope early so it can include all their parameters.
-                self.function_body = true;
-                self.scopes.push(HashMap::new());
-
-                // Copied from visit::visit_function_definition to replace the declarator visit with only visiting the derived declarators (the function name is not a variable).
-                for derived_declarator in &function_definition.declarator.node.derived {
-                    self.visit_derived_declarator(
-                        &derived_declarator.node,
-                        &derived_declarator.span,
-                    )
-                }
-                for specifier in &function_definition.specifiers {
-                    self.visit_declaration_specifier(&specifier.node, &specifier.span);
-                }
-                for declaration in &function_definition.declarations {
-                    self.visit_declaration(&declaration.node, &declaration.span);
-                }
-                self.visit_statement(
-                    &function_definition.statement.node,
-                    &function_definition.statement.span,
-                );
+            if self.functions_to_check.matches(&id.node.name) {
+                // Each function starts from the global scope as it was before any function was checked.
+                self.current_function = id.node.name.clone();
+                let global_scope = self.scopes[0].clone();
+                self.check_function(function_definition);
+                self.scopes = vec![global_scope];
+                self.current_function.clear();
             }
         }
     }
 
-    // Scope changes.
+    // Scope changes. Only reached for blocks inside expressions, function bodies are split into a control flow graph.
     fn visit_statement(&mut self, statement: &'ast Statement, span: &'ast span::Span) {
         // Add a new scope layer for this block.
-        if !self.function_body {
-            if let Statement::Compound(_) = statement {
-                self.scopes.push(HashMap::new());
-            }
+        if let Statement::Compound(_) = statement {
+            self.scopes.push(HashMap::new());
         }
-        self.function_body = false;
 
         // Run the block.
         let before_scope = self.scopes.clone();
@@ -122,27 +120,9 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
         }
     }
 
-    // If statements - different from loop scopes because the if and else blocks have to be treated equally.
-    fn visit_if_statement(&mut self, if_statement: &'ast IfStatement, _: &'ast span::Span) {
-        self.visit_expression(&if_statement.condition.node, &if_statement.condition.span);
-
-        let temp = self.scopes.clone();
-        self.visit_statement(
-            &if_statement.then_statement.node,
-            &if_statement.then_statement.span,
-        );
-        if let Some(ref else_statement) = if_statement.else_statement {
-            let then_scopes = self.scopes.clone();
-
-            // Runs the else block as if the if block has not yet been run.
-            self.scopes = temp;
-            self.visit_statement(&else_statement.node, &else_statement.span);
-            self.merge_scopes(&then_scopes);
-        }
-    }
-
     // Variable declarations.
     fn visit_declaration(&mut self, declaration: &'ast Declaration, _: &'ast span::Span) {
+        self.declare_enumerators(&declaration.specifiers);
         for declarator in &declaration.declarators {
             self.declare_variable(
                 &declarator.node.declarator.node,
@@ -187,7 +167,9 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
         boe: &'ast ast::BinaryOperatorExpression,
         span: &'ast span::Span,
     ) {
-        if boe.operator.node != BinaryOperator::Assign {
+        if boe.operator.node == BinaryOperator::Index {
+            self.visit_index(boe);
+        } else if boe.operator.node != BinaryOperator::Assign {
             visit::visit_binary_operator_expression(self, boe, span);
         } else {
             self.set_expression_ownership(&boe.rhs, false, span);
@@ -200,6 +182,12 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
                     // member identifier is known from when it was set to valid in set_expression_is_valid.
                     self.add_reference(self.member_identifier.clone(), &boe.rhs, span);
                 }
+                Expression::BinaryOperator(_) => {
+                    // arr[i] = ... assigns to the array.
+                    if let Some(array_name) = self.expression_name(&boe.lhs) {
+                        self.add_reference(array_name, &boe.rhs, span);
+                    }
+                }
                 _ => {}
             }
         }
@@ -223,36 +211,68 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
         span: &'ast span::Span,
     ) {
         // Does not visit the function name expression, to avoid creating a variable for the function identifier.
-        let Expression::Identifier(function_id) = &call_expression.callee.node else {
-            return;
+        // Calls through function pointers have no name, so they are treated like calls to undeclared functions.
+        let function_name = match &call_expression.callee.node {
+            Expression::Identifier(function_id) => Some(function_id.node.name.clone()),
+            _ => None,
         };
 
-        let function_name = &function_id.node.name;
-        let Some(function_parameters) = self.functions.get(function_name) else {
-            println!("ISSUE: Function name '{function_name}' not defined!");
-            return;
-        };
-        let parameters_clone = function_parameters.clone();
+        // Heap functions do not need to be declared. Their results are handled in add_reference.
+        if let Some(function_name) = &function_name {
+            let frees = self.heap_functions.deallocators.contains(function_name)
+                || self.heap_functions.reallocators.contains(function_name);
+            if frees || self.heap_functions.allocators.contains(function_name) {
+                for (argument_index, argument) in call_expression.arguments.iter().enumerate() {
+                    if frees && argument_index == 0 {
+                        self.free(argument, span);
+                    } else {
+                        self.visit_expression(&argument.node, &argument.span);
+                    }
+                }
+                return;
+            }
+        }
+        let (parameters_clone, summary) =
+            match function_name.as_ref().and_then(|f| self.functions.get(f)) {
+                Some(function_parameters) => (
+                    function_parameters.clone(),
+                    function_name
+                        .as_ref()
+                        .and_then(|f| self.summaries.get(f))
+                        .cloned(),
+                ),
+                None => self.unknown_function(
+                    function_name.as_deref(),
+                    call_expression.arguments.len(),
+                    span,
+                ),
+            };
         let mut argument_index = 0;
 
         // Decide which action to take on each of the function's arguments.
         for argument in &call_expression.arguments {
+            // Arrays decay to a pointer to their first element, so passing one borrows it like &arr.
+            if let Some(array_id) = self.decayed_array(argument) {
+                self.visit_expression(&argument.node, &argument.span);
+                self.borrow_argument(&array_id, parameters_clone.get(argument_index));
+                argument_index += 1;
+                continue;
+            }
             match &argument.node {
                 Expression::UnaryOperator(uo) => {
                     if UnaryOperator::Address == uo.node.operator.node {
-                        if let Expression::Identifier(identifier) = &uo.node.operand.node {
-                            // The argument looks like &x.
-                            if argument_index > parameters_clone.len()
-                                || matches!(parameters_clone[argument_index], VarType::MutRef(_))
-                            {
-                                // Passing a mutable reference makes all previous mut and const references invalid.
-                                let var = self.name_to_mut_var(&identifier.node.name);
-                                var.const_refs.clear();
-                                var.mut_refs.clear();
-                            } else {
-                                // Passing a const reference makes all previous mut references invalid.
-                                let var = self.name_to_mut_var(&identifier.node.name);
-                                var.mut_refs.clear();
+                        // The argument looks like &x, &s.y or &arr[i]. Borrowing a moved value is an error too.
+                        // Borrowing through a pointer, like &p->y, reborrows it and leaves it valid.
+                        if let Some(name) = self.expression_name(&uo.node.operand) {
+                            self.announce_partial_move(&name, &uo.node.operand.span);
+                            self.announce_no_ownership(name, &uo.node.operand.span);
+                        }
+                        if Self::is_place(&uo.node.operand) {
+                            if let Some(borrowed_id) = self.borrowed_variable(&uo.node.operand) {
+                                self.borrow_argument(
+                                    &borrowed_id,
+                                    parameters_clone.get(argument_index),
+                                );
                             }
                         }
                     } else {
@@ -266,9 +286,30 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
                 }
                 _ => {
                     // If not a reference, try to set as not owner. Won't do anything if it isn't an Owner type.
-                    // Occassionally causes double error prints.
+                    // Uses the argument's span so a use-after-move found by both steps is only reported once.
                     self.visit_expression(&argument.node, &argument.span);
-                    self.set_expression_ownership(argument, false, span);
+
+                    // Owning pointers are only moved into parameters that own them, other functions borrow through them.
+                    // Functions defined in the file only move the arguments their summary says they consume.
+                    let owning_parameter = matches!(
+                        parameters_clone.get(argument_index),
+                        Some(VarType::OwnedPtr(_, _))
+                    );
+                    let consumed = owning_parameter
+                        || summary.as_ref().is_some_and(|s| {
+                            s.parameters.get(argument_index) == Some(&ParameterMode::Consumed)
+                        });
+                    let borrowed = !consumed
+                        && (summary.is_some()
+                            || self.expression_name(argument).is_some_and(|name| {
+                                matches!(self.name_to_var(&name).var_type, VarType::OwnedPtr(_, _))
+                            }));
+                    if consumed {
+                        self.consume(argument, &argument.span);
+                    }
+                    if !borrowed {
+                        self.set_expression_ownership(argument, false, &argument.span);
+                    }
                 }
             }
             argument_index += 1;
@@ -291,6 +332,7 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
         } else {
             // Non-struct member identifier.
             self.announce_no_ownership(identifier.name.clone(), span);
+            self.announce_partial_move(&identifier.name, span);
             self.announce_invalid_reference(identifier.name.clone(), span);
         }
     }
@@ -310,13 +352,28 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
         );
         self.member_count -= 1;
 
-        self.member_identifier_pieces
-            .push(member_expression.identifier.node.name.clone());
+        // p->x is (*p).x: p was checked like any other use, and is replaced by what it points to. The name follows
+        // the first target, and the field of every other target p may point to is checked here.
+        let field = &member_expression.identifier.node.name;
+        if member_expression.operator.node == MemberOperator::Indirect {
+            let pointer = self.member_identifier_pieces.join(".");
+            let mut targets = self.dereference_targets(&pointer).into_iter();
+            self.member_identifier_pieces = vec![targets.next().unwrap_or_default()];
+            for target in targets {
+                self.announce_no_ownership(format!("{target}.{field}"), span);
+                self.announce_invalid_reference(format!("{target}.{field}"), span);
+            }
+        }
+        self.member_identifier_pieces.push(field.clone());
 
         if self.member_count > 0 || !self.mute_member_expression {
             // These run every time, except possibly the last step if muted.
             let partial_name = self.member_identifier_pieces.join(".");
             self.announce_no_ownership(partial_name.clone(), span);
+            if self.member_count == 0 {
+                // The whole member is used, not only a piece of the name on the way to a field.
+                self.announce_partial_move(&partial_name, span);
+            }
             self.announce_invalid_reference(partial_name, span);
         }
         if self.member_count == 0 {
@@ -337,33 +394,45 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
                 self.dereference_name.clear();
                 match &uoe.operand.node {
                     Expression::Identifier(id) => {
+                        // Owning pointers that were moved can not be dereferenced.
+                        self.announce_no_ownership(id.node.name.to_string(), span);
                         self.announce_invalid_reference(id.node.name.to_string(), span);
-                        let var = self.name_to_var(&id.node.name);
-                        match &var.var_type {
-                            VarType::ConstRef(points_to) | VarType::MutRef(points_to) => {
-                                let pointed_to = points_to.iter().next().unwrap();
-                                self.dereference_name = pointed_to.name.clone();
-                            }
-                            _ => {}
-                        }
+                        self.dereference_name = self.dereference_target(&id.node.name);
                     }
                     Expression::Member(member_expression) => {
                         let member_pieces_backup = self.member_identifier_pieces.clone();
                         let member_count_backup = self.member_count;
                         self.get_member_expression_identifier(member_expression);
+                        self.announce_no_ownership(self.member_identifier.clone(), span);
                         self.announce_invalid_reference(self.member_identifier.clone(), span);
-                        let var = self.name_to_var(&self.member_identifier.clone());
-                        match &var.var_type {
-                            VarType::ConstRef(points_to) | VarType::MutRef(points_to) => {
-                                let pointed_to = points_to.iter().next().unwrap();
-                                self.dereference_name = pointed_to.name.clone();
-                            }
-                            _ => {}
+                        self.dereference_name =
+                            self.dereference_target(&self.member_identifier.clone());
+                        self.member_identifier_pieces = member_pieces_backup;
+                        self.member_count = member_count_backup;
+                    }
+                    Expression::BinaryOperator(boe)
+                        if boe.node.operator.node == BinaryOperator::Index =>
+                    {
+                        // *arr[i] dereferences any of the pointers in arr.
+                        let member_pieces_backup =
+                            std::mem::take(&mut self.member_identifier_pieces);
+                        let member_count_backup = std::mem::replace(&mut self.member_count, 0);
+                        self.visit_index(&boe.node);
+                        if let Some(array_name) = self.expression_name(&uoe.operand) {
+                            self.dereference_name = self.dereference_target(&array_name);
                         }
                         self.member_identifier_pieces = member_pieces_backup;
                         self.member_count = member_count_backup;
                     }
-                    _ => self.visit_expression(&uoe.operand.node, &uoe.operand.span),
+                    _ => {
+                        self.visit_expression(&uoe.operand.node, &uoe.operand.span);
+                        // *(p + 1) dereferences what p points to.
+                        if let Some(base) = self.arithmetic_base(&uoe.operand) {
+                            if let Some(pointer) = self.base_pointer_name(base) {
+                                self.dereference_name = self.dereference_target(&pointer);
+                            }
+                        }
+                    }
                 }
             }
             _ => visit::visit_unary_operator_expression(self, uoe, span),
@@ -374,19 +443,4 @@ impl<'ast, 'a> visit::Visit<'ast> for BorrowChecker<'a> {
                 .push(self.dereference_name.clone());
         }
     }
-
-    // For printing requested outputs on each line. Requires each line be a new block item, which requires {} around every block.
-    fn visit_block_item(&mut self, block_item: &'ast BlockItem, span: &'ast span::Span) {
-        match self.set_prints {
-            PrintType::Ownership => self.print_ownership(span),
-            PrintType::Reference => self.print_references(span),
-            PrintType::ErrorOnly => {}
-        }
-        visit::visit_block_item(self, block_item, span);
-        match self.set_prints {
-            PrintType::Ownership => self.print_ownership(span),
-            PrintType::Reference => self.print_references(span),
-            PrintType::ErrorOnly => {}
-        }
-    }
 }
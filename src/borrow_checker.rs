@@ -1,21 +1,86 @@
+use crate::diagnostic::Label;
+use crate::diagnostic::*;
+use crate::heap::HeapFunctions;
+use crate::spec::Spec;
+use crate::summary::FunctionSummary;
 use crate::variable::*;
 use lang_c::ast::*;
 use lang_c::loc::*;
 use lang_c::span::*;
 use lang_c::visit::Visit;
 use lang_c::*;
+use regex::Regex;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+// Known noreturn functions from the C library and the Linux kernel, which are often called without a declaration.
+const NORETURN_FUNCTIONS: [&str; 8] = [
+    "exit",
+    "_Exit",
+    "quick_exit",
+    "abort",
+    "longjmp",
+    "panic",
+    "BUG",
+    "__builtin_unreachable",
+];
+
+#[derive(Clone, Copy)]
 pub enum PrintType {
     Ownership,
     Reference,
     ErrorOnly,
 }
 
+// What a call to a function that was never declared does with its arguments.
+#[derive(Clone, Copy)]
+pub enum UnknownFunctions {
+    // Owner arguments are moved, and pointer arguments are borrowed mutably.
+    Conservative,
+    // Every argument is only borrowed.
+    Permissive,
+    // The call is an error, and its arguments are only borrowed.
+    Error,
+}
+
+// Decides which function definitions are checked.
+pub struct FunctionFilter {
+    // Functions named explicitly are always checked.
+    pub names: Vec<String>,
+    // Check every function definition, unless excluded.
+    pub all: bool,
+    pub include: Vec<Regex>,
+    pub exclude: Vec<Regex>,
+}
+
+// Everything about how the checker runs that comes from the command line.
+pub struct CheckerConfig {
+    pub to_check: FunctionFilter,
+    pub heap_functions: HeapFunctions,
+    pub owned_pointer_names: Option<Regex>,
+    pub unknown_functions: UnknownFunctions,
+    pub allow_index_moves: bool,
+    pub print_global_scope_sets: bool,
+    pub set_prints: PrintType,
+    pub event_prints: PrintType,
+}
+
+impl FunctionFilter {
+    pub fn matches(&self, name: &str) -> bool {
+        if self.names.iter().any(|n| n == name) {
+            return true;
+        }
+        (self.all || self.include.iter().any(|r| r.is_match(name)))
+            && !self.exclude.iter().any(|r| r.is_match(name))
+    }
+}
+
 pub struct BorrowChecker<'a> {
     // For the user to specify what functions the checks should run on.
-    pub functions_to_check: Vec<String>,
+    pub functions_to_check: FunctionFilter,
+
+    // The function definition currently being checked.
+    pub current_function: String,
 
     // Needed for line numbers in prints.
     pub src: &'a str,
@@ -26,24 +91,68 @@ pub struct BorrowChecker<'a> {
     // So the checker knows the types of struct members (need to know if they are copy types or not)
     // and function parameters (need to know if they are marked const in the function header).
     pub structs: HashMap<String, HashMap<String, VarType>>,
+    // Struct fields annotated nullable, and struct fields that are arrays, as struct_name.field_name.
+    pub nullable_fields: HashSet<String>,
+    pub array_fields: HashSet<String>,
+    // Struct fields that point to structs, as struct_name.field_name, and the struct they point to.
+    pub pointee_structs: HashMap<String, String>,
+    // The struct types that are unions, whose members share their memory.
+    pub unions: HashSet<String>,
     pub functions: HashMap<String, Vec<VarType>>,
 
+    // The ownership of functions and struct fields, from spec files instead of annotations.
+    pub spec: &'a Spec,
+
+    // The parameters each function's returned pointer borrows from, according to its signature.
+    pub lifetimes: HashMap<String, Vec<usize>>,
+    // The annotations on each function's returned pointer that change what its callers get: borrowed, borrowed_mut
+    // and nullable.
+    pub result_annotations: HashMap<String, Vec<String>>,
+
+    // The parameters that the signature of the function being checked does not let it return references into.
+    pub unreturnable_parameters: HashSet<String>,
+
+    // What each function defined in the file does with its parameters and return value, found from its body.
+    pub summaries: HashMap<String, FunctionSummary>,
+
+    // The variables that the values returned so far by the function being checked point to.
+    pub return_points_to: HashSet<Id>,
+
+    // Functions that never return, so no path continues past a call to them.
+    pub noreturn_functions: HashSet<String>,
+
+    // Functions that allocate and free heap memory.
+    pub heap_functions: HeapFunctions,
+
+    // Pointer variables with matching names own what they point to.
+    pub owned_pointer_names: Option<Regex>,
+
+    // What calls to undeclared functions do, and whether moving out of an array element moves the whole array
+    // instead of being an error.
+    pub unknown_functions: UnknownFunctions,
+    pub allow_index_moves: bool,
+
     // Struct member identifier compilation.
     pub mute_member_expression: bool,
     pub member_count: u32,
     pub member_identifier_pieces: Vec<String>,
     pub member_identifier: String,
 
-    // Function body scope creation is handled in the function definition block to include the parameters.
-    // This stops visit_statement from creating another new scope.
-    pub function_body: bool,
-
     // Tracks the previous struct name seen, for when pointers to structs are declared as function parameters.
     pub previous_struct_name: String,
 
     // The last variable name to be dereferenced (if *p->x, seeing *p stores x in this field).
     pub dereference_name: String,
 
+    // Every problem found so far, in the order they were found.
+    pub diagnostics: Vec<Diagnostic>,
+
+    // Stops all diagnostics and prints while the fixed point of a control flow graph is being found.
+    pub silent: bool,
+
+    // The number of internal issues found so far, even while silent.
+    pub issues: usize,
+
     // Controls what kind of output is shown.
     print_global_scope_sets: bool,
     pub set_prints: PrintType,
@@ -51,38 +160,122 @@ pub struct BorrowChecker<'a> {
 }
 
 impl<'a> BorrowChecker<'a> {
-    pub fn new(
-        to_check: Vec<String>,
-        source: &'a str,
-        print_global_scope_sets: bool,
-        set_prints: PrintType,
-        event_prints: PrintType,
-    ) -> Self {
-        BorrowChecker {
-            functions_to_check: to_check,
+    pub fn new(config: CheckerConfig, spec: &'a Spec, source: &'a str) -> Self {
+        let mut borrow_checker = BorrowChecker {
+            functions_to_check: config.to_check,
+            current_function: "".to_string(),
 
             src: source,
             scopes: vec![HashMap::new()],
 
             structs: HashMap::new(),
+            nullable_fields: HashSet::new(),
+            array_fields: HashSet::new(),
+            pointee_structs: HashMap::new(),
+            unions: HashSet::new(),
             functions: HashMap::new(),
+            spec,
+
+            lifetimes: HashMap::new(),
+            result_annotations: HashMap::new(),
+            unreturnable_parameters: HashSet::new(),
+            summaries: HashMap::new(),
+            return_points_to: HashSet::new(),
+
+            noreturn_functions: NORETURN_FUNCTIONS.iter().map(|f| f.to_string()).collect(),
+
+            heap_functions: config.heap_functions,
+            owned_pointer_names: config.owned_pointer_names,
+            unknown_functions: config.unknown_functions,
+            allow_index_moves: config.allow_index_moves,
 
             mute_member_expression: false,
             member_count: 0,
             member_identifier_pieces: Vec::new(),
             member_identifier: "".to_string(),
 
-            function_body: false,
-
             previous_struct_name: "".to_string(),
 
             dereference_name: "".to_string(),
 
-            print_global_scope_sets: print_global_scope_sets,
-            set_prints: set_prints,
-            event_prints: event_prints,
+            diagnostics: Vec::new(),
+
+            silent: false,
+
+            issues: 0,
+
+            print_global_scope_sets: config.print_global_scope_sets,
+            set_prints: config.set_prints,
+            event_prints: config.event_prints,
+        };
+        borrow_checker.add_function_specs();
+        borrow_checker
+    }
+
+    // Records a problem found in the function currently being checked. The same problem is only recorded once.
+    pub fn report(
+        &mut self,
+        code: ErrorCode,
+        span: &span::Span,
+        message: String,
+        labels: Vec<Label>,
+        variables: Vec<String>,
+    ) {
+        if self.silent {
+            return;
+        }
+        let diagnostic = Diagnostic {
+            code,
+            severity: code.severity(),
+            span: *span,
+            message,
+            labels,
+            variables,
+            function: self.current_function.clone(),
+        };
+        let duplicate = self.diagnostics.iter().any(|d| {
+            d.code == diagnostic.code
+                && d.span.start == diagnostic.span.start
+                && d.message == diagnostic.message
+        });
+        if !duplicate {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    // A diagnostic label at the given location, if it is known.
+    pub fn label(span: Option<span::Span>, message: &str) -> Vec<Label> {
+        span.into_iter()
+            .map(|span| Label {
+                span,
+                message: message.to_string(),
+            })
+            .collect()
+    }
+
+    // Points to where a value used at span was moved. Moves at the same place happened in an earlier loop iteration.
+    pub fn moved_label(killed_at: Option<span::Span>, span: &span::Span) -> Vec<Label> {
+        if killed_at.is_some_and(|killed_at| killed_at.start == span.start) {
+            Self::label(killed_at, "value moved here, in previous iteration of loop")
+        } else {
+            Self::label(killed_at, "value moved here")
+        }
+    }
+
+    // Prints a problem with the checker itself (not the input program), like a construct it does not understand.
+    pub fn issue(&mut self, message: String) {
+        self.issues += 1;
+        if !self.silent {
+            eprintln!("ISSUE: {message}");
         }
     }
+
+    // Whether any errors (not just warnings) were found.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
 }
 
 // Functions that mutate and print information about the ownership of variables.
@@ -104,6 +297,20 @@ impl<'a> BorrowChecker<'a> {
         return count;
     }
 
+    // The variable a name is, or is a member of: s for s.x.y. The unknown global that a struct member pointer like s.p
+    // points to is named ?s.p, with a dot, so it is the variable for ?s.p.x, and ?s is not a variable at all.
+    pub fn root_variable(&self, name: &str) -> Option<String> {
+        let root = name.split('.').next().unwrap_or_default();
+        if self.scopes.iter().any(|scope| scope.contains_key(root)) {
+            return Some(root.to_string());
+        }
+        self.scopes[0]
+            .keys()
+            .filter(|global| *global == name || name.starts_with(&format!("{global}.")))
+            .max_by_key(|global| global.len())
+            .cloned()
+    }
+
     pub fn get_id(&self, name: &str) -> Id {
         return Id {
             name: name.to_string(),
@@ -111,6 +318,11 @@ impl<'a> BorrowChecker<'a> {
         };
     }
 
+    // Whether the variable with this id has not gone out of scope (or been replaced by a new scope at the same level).
+    pub fn is_in_scope(&self, id: &Id) -> bool {
+        id.scope < self.scopes.len() && self.scopes[id.scope].contains_key(&id.name)
+    }
+
     pub fn id_to_var(&self, id: &Id) -> &Variable {
         return self.scopes[id.scope].get(&id.name).unwrap();
     }
@@ -125,11 +337,12 @@ impl<'a> BorrowChecker<'a> {
         if !self.scopes[count].contains_key(name) {
             let var_type = self.get_member_var_type(name);
             // println!("Created new variable '{name}' of type {:?}", var_type);
-            self.scopes[count].insert(
-                name.to_string(),
-                Variable::new(name.to_string(), count, var_type.clone()),
-            );
-            self.declare_unknown_global(name, var_type, false)
+            let mut variable = Variable::new(name.to_string(), count, var_type.clone());
+            variable.nullable = self.is_nullable_member(name);
+            variable.array = self.is_array_member(name);
+            self.scopes[count].insert(name.to_string(), variable);
+            let pointee = self.member_pointee(name);
+            self.declare_unknown_global(name, var_type, &pointee, false)
         }
         return self.scopes[count].get(name).unwrap();
     }
@@ -139,11 +352,12 @@ impl<'a> BorrowChecker<'a> {
         if !self.scopes[count].contains_key(name) {
             let var_type = self.get_member_var_type(name);
             // println!("Created new variable '{name}' of type {:?}", var_type);
-            self.scopes[count].insert(
-                name.to_string(),
-                Variable::new(name.to_string(), count, var_type.clone()),
-            );
-            self.declare_unknown_global(name, var_type, false)
+            let mut variable = Variable::new(name.to_string(), count, var_type.clone());
+            variable.nullable = self.is_nullable_member(name);
+            variable.array = self.is_array_member(name);
+            self.scopes[count].insert(name.to_string(), variable);
+            let pointee = self.member_pointee(name);
+            self.declare_unknown_global(name, var_type, &pointee, false)
         }
         return self.scopes[count].get_mut(name).unwrap();
     }
@@ -154,28 +368,40 @@ impl<'a> BorrowChecker<'a> {
             return;
         }
         let variable: &mut Variable = self.name_to_mut_var(&name);
-        let var_type: VarType = variable.var_type.clone();
+        let killed_at = variable.killed_at;
 
         // Changing the ownership of a value invalidates all of its references.
         variable.const_refs.clear();
         variable.mut_refs.clear();
 
-        // If the variable is an Owner, additional checks to set its ownership and print error messages.
-        if let VarType::Owner(type_name, had_ownership) = var_type {
-            variable.var_type = VarType::Owner(type_name, has_ownership);
+        // If the variable is an Owner or owning pointer, additional checks to set its ownership and print error messages.
+        let had_ownership = match &mut variable.var_type {
+            VarType::Owner(_, owned) | VarType::OwnedPtr(_, owned) => {
+                Some(std::mem::replace(owned, has_ownership))
+            }
+            _ => None,
+        };
+        if let Some(had_ownership) = had_ownership {
+            if !has_ownership && had_ownership {
+                variable.killed_at = Some(*span);
+            }
 
             // Error / Debug prints.
             let (location, _) = get_location_for_offset(self.src, span.start);
-            if has_ownership && matches!(self.event_prints, PrintType::Ownership) {
+            let event_prints = matches!(self.event_prints, PrintType::Ownership) && !self.silent;
+            if has_ownership && event_prints {
                 println!("Made live '{}' on line {}.", name, location.line);
             } else if !has_ownership {
                 if !had_ownership {
-                    println!(
-                        "ERROR: Use of moved value '{}' on line {}.",
-                        name, location.line
+                    self.report(
+                        ErrorCode::UseAfterMove,
+                        span,
+                        format!("Use of moved value '{name}'"),
+                        Self::moved_label(killed_at, span),
+                        vec![name],
                     );
                 } else {
-                    if matches!(self.event_prints, PrintType::Ownership) {
+                    if event_prints {
                         println!("Killed '{}' on line {}.", name, location.line);
                     }
                 }
@@ -184,7 +410,9 @@ impl<'a> BorrowChecker<'a> {
     }
 
     // For when an entire struct has its ownership changed.
-    fn set_all_ownership(&mut self, name: String, has_ownership: bool, span: &span::Span) {
+    pub fn set_all_ownership(&mut self, name: String, has_ownership: bool, span: &span::Span) {
+        // Moving a partially moved struct is one error for the whole struct, not one for each field moved before.
+        let partially_moved = !has_ownership && self.announce_partial_move(&name, span);
         self.set_ownership(name.clone(), has_ownership, span);
 
         // Moves ownership (and invalidates all pointers to) local struct relatives. (assigning to x invalidates x.y).
@@ -195,6 +423,9 @@ impl<'a> BorrowChecker<'a> {
             .map(|k| k.to_string())
             .collect();
         for relative in local_relatives {
+            if partially_moved && self.is_moved(&relative) {
+                continue;
+            }
             self.set_ownership(relative, has_ownership, span);
         }
 
@@ -210,6 +441,72 @@ impl<'a> BorrowChecker<'a> {
         }
     }
 
+    pub fn is_moved(&mut self, name: &str) -> bool {
+        matches!(
+            self.name_to_var(name).var_type,
+            VarType::Owner(_, false) | VarType::OwnedPtr(_, false)
+        )
+    }
+
+    // The fields that were moved out of a struct, with where they were moved, in order. The fields of a moved field
+    // were moved with it, so they are not listed.
+    pub fn moved_fields(&self, name: &str) -> Vec<(String, Option<span::Span>)> {
+        let member = name.to_string() + ".";
+        let mut moved: Vec<(String, Option<span::Span>)> = self.scopes[self.get_scope_number(name)]
+            .iter()
+            .filter(|(field, variable)| {
+                field.starts_with(&member)
+                    && matches!(
+                        variable.var_type,
+                        VarType::Owner(_, false) | VarType::OwnedPtr(_, false)
+                    )
+            })
+            .map(|(field, variable)| (field.clone(), variable.killed_at))
+            .collect();
+        moved.sort_by(|a, b| a.0.cmp(&b.0));
+        let fields: Vec<String> = moved.iter().map(|(field, _)| field.clone() + ".").collect();
+        moved.retain(|(field, _)| !fields.iter().any(|parent| field.starts_with(parent)));
+        moved
+    }
+
+    // Error for using a whole struct after some of its fields were moved out of it, like Rust's "use of partially
+    // moved value". Returns whether the struct was partially moved.
+    pub fn announce_partial_move(&mut self, name: &str, span: &span::Span) -> bool {
+        if name == "NULL" || !matches!(self.name_to_var(name).var_type, VarType::Owner(_, true)) {
+            return false;
+        }
+        let moved = self.moved_fields(name);
+        let Some(((last, _), rest)) = moved.split_last() else {
+            return false;
+        };
+        let fields = if rest.is_empty() {
+            format!("'{last}' was moved")
+        } else {
+            let rest: String = rest
+                .iter()
+                .map(|(field, _)| format!("'{field}'"))
+                .intersperse(", ".to_string())
+                .collect();
+            format!("{rest} and '{last}' were moved")
+        };
+        let labels = moved
+            .iter()
+            .flat_map(|(field, killed_at)| {
+                Self::label(*killed_at, &format!("'{field}' moved here"))
+            })
+            .collect();
+        let mut variables = vec![name.to_string()];
+        variables.extend(moved.iter().map(|(field, _)| field.clone()));
+        self.report(
+            ErrorCode::UseAfterMove,
+            span,
+            format!("Use of partially moved value '{name}': {fields}"),
+            labels,
+            variables,
+        );
+        true
+    }
+
     // Based on the DeclarationSpecifiers present at the variable's declaration, determine what its VarType should be.
     pub fn get_var_type(
         &mut self,
@@ -220,7 +517,7 @@ impl<'a> BorrowChecker<'a> {
         if !declarator.derived.is_empty()
             && matches!(&declarator.derived[0].node, DerivedDeclarator::Pointer(_))
         {
-            // The first derived declarator says this variable is a pointer (Arrays not yet supported).
+            // The first derived declarator says this variable is a pointer (for arrays, that their elements are).
             var_type = VarType::MutRef(HashSet::new());
             self.previous_struct_name.clear();
             for specifier in specifiers {
@@ -253,6 +550,18 @@ impl<'a> BorrowChecker<'a> {
                     _ => {}
                 }
             }
+
+            // Pointers can be marked as owning through an annotation or their name.
+            let owned_name = match &declarator.kind.node {
+                DeclaratorKind::Identifier(id) => self
+                    .owned_pointer_names
+                    .as_ref()
+                    .is_some_and(|r| r.is_match(&id.node.name)),
+                _ => false,
+            };
+            if owned_name {
+                var_type = VarType::OwnedPtr(HashSet::new(), true);
+            }
         } else {
             // Either a copy type or an owner type.
             for specifier in specifiers {
@@ -271,34 +580,97 @@ impl<'a> BorrowChecker<'a> {
                                 var_type = VarType::Owner(type_name, true);
                             }
                         }
+                        // Enums are integers.
+                        TypeSpecifier::Enum(_) => var_type = VarType::Copy,
                         _ => {}
                     }
                 }
             }
         }
+
+        // Annotations say what the type cannot, so they override what was inferred from it.
+        for annotation in Self::annotations(declarator, specifiers) {
+            var_type = Self::annotated_type(var_type, &annotation);
+        }
         return var_type;
     }
 
+    // The VarType of a variable with an annotation, from the VarType inferred from its declaration.
+    pub fn annotated_type(var_type: VarType, annotation: &str) -> VarType {
+        let is_pointer = var_type.points_to().is_some();
+        match (annotation, is_pointer) {
+            ("owned", true) => VarType::OwnedPtr(HashSet::new(), true),
+            ("owned", false) => match var_type {
+                VarType::Owner(_, _) => var_type,
+                _ => VarType::Owner(String::new(), true),
+            },
+            ("borrowed", true) => VarType::ConstRef(HashSet::new()),
+            ("borrowed_mut", true) => VarType::MutRef(HashSet::new()),
+            ("copy", _) => VarType::Copy,
+            _ => var_type,
+        }
+    }
+
+    // The field a struct member is, as struct_name.field_name.
+    fn member_field(&mut self, name: &str) -> Option<String> {
+        let dot = name.rfind('.')?;
+        match &self.name_to_var(&name[..dot]).var_type {
+            VarType::Owner(struct_name, _) => Some(format!("{struct_name}.{}", &name[dot + 1..])),
+            _ => None,
+        }
+    }
+
+    // The struct a struct member points to, from the declaration of its field, or nothing if it is not a pointer to
+    // a struct.
+    fn member_pointee(&mut self, name: &str) -> String {
+        self.member_field(name)
+            .and_then(|field| self.pointee_structs.get(&field).cloned())
+            .unwrap_or_default()
+    }
+
+    // Whether a struct member was declared nullable in its struct.
+    fn is_nullable_member(&mut self, name: &str) -> bool {
+        self.member_field(name)
+            .is_some_and(|field| self.nullable_fields.contains(&field))
+    }
+
+    // Whether a struct member is an array, or a member of the elements of an array (arr[i].x is arr.x).
+    fn is_array_member(&mut self, name: &str) -> bool {
+        let Some(dot) = name.rfind('.') else {
+            return false;
+        };
+        self.name_to_var(&name[..dot]).array
+            || self
+                .member_field(name)
+                .is_some_and(|field| self.array_fields.contains(&field))
+    }
+
     // Most struct members are not explicitly declared. We infer their VarTypes from the types of their parent struct's fields.
     pub fn get_member_var_type(&mut self, name: &str) -> VarType {
         if !name.contains(".") {
-            println!("ISSUE: Unrecognized name '{name}' was not a struct member!");
+            self.issue(format!(
+                "Unrecognized name '{name}' was not a struct member!"
+            ));
             return VarType::Copy;
         }
         let final_name = &name[name.rfind('.').unwrap() + 1..];
         let parent_name = &name[..name.rfind('.').unwrap()];
         let parent_type = self.name_to_var(parent_name).var_type.clone();
         if let VarType::Owner(struct_name, _) = parent_type {
-            let fields = self
-                .structs
-                .get(&struct_name.to_string())
-                .expect("ISSUE: No struct of specified type");
-            return fields
-                .get(&final_name.to_string())
-                .expect("ISSUE: Parent struct had no matching field!")
-                .clone();
-        }
-        println!("ISSUE: '{parent_name}' is not an owner (struct) type");
+            // Owners that are not structs (annotated owned) have no fields.
+            let Some(fields) = self.structs.get(&struct_name.to_string()) else {
+                self.issue(format!("'{parent_name}' is not a struct"));
+                return VarType::Copy;
+            };
+            let Some(var_type) = fields.get(&final_name.to_string()) else {
+                self.issue(format!(
+                    "Struct '{struct_name}' has no field '{final_name}'"
+                ));
+                return VarType::Copy;
+            };
+            return var_type.clone();
+        }
+        self.issue(format!("'{parent_name}' is not an owner (struct) type"));
         return VarType::Copy;
     }
 
@@ -306,6 +678,10 @@ impl<'a> BorrowChecker<'a> {
     pub fn add_struct(&mut self, declaration: &Node<Declaration>) {
         let mut struct_names = HashSet::new();
         let mut struct_members: HashMap<String, VarType> = HashMap::new();
+        let mut nullable_members = Vec::new();
+        let mut array_members = Vec::new();
+        let mut pointee_members = Vec::new();
+        let mut is_union = false;
         for specifier in &declaration.node.specifiers {
             let DeclarationSpecifier::TypeSpecifier(type_specifier) = &specifier.node else {
                 continue;
@@ -314,6 +690,7 @@ impl<'a> BorrowChecker<'a> {
             let TypeSpecifier::Struct(struct_type) = &type_specifier.node else {
                 continue;
             };
+            is_union |= struct_type.node.kind.node == StructKind::Union;
 
             if let Some(id) = &struct_type.node.identifier {
                 struct_names.insert(id.node.name.clone());
@@ -328,14 +705,32 @@ impl<'a> BorrowChecker<'a> {
                 let StructDeclaration::Field(field) = &struct_declaration.node else {
                     continue;
                 };
+                // A member like enum { A, B } e; declares its constants like any other enum.
+                let specifiers =
+                    self.struct_specifier_to_declaration_specifier(&field.node.specifiers);
+                self.declare_enumerators(&specifiers);
                 for struct_declarator in &field.node.declarators {
                     if let Some(field_declarator) = &struct_declarator.node.declarator {
-                        let var_type = self.get_var_type(
-                            &field_declarator.node,
-                            &self.struct_specifier_to_declaration_specifier(&field.node.specifiers),
-                        );
+                        let var_type = self.get_var_type(&field_declarator.node, &specifiers);
                         if let DeclaratorKind::Identifier(id) = &field_declarator.node.kind.node {
+                            if var_type.points_to().is_some()
+                                && !self.previous_struct_name.is_empty()
+                            {
+                                pointee_members.push((
+                                    id.node.name.clone(),
+                                    self.previous_struct_name.clone(),
+                                ));
+                            }
                             struct_members.insert(id.node.name.clone(), var_type);
+                            if Self::is_array_declarator(&field_declarator.node) {
+                                array_members.push(id.node.name.clone());
+                            }
+                            if Self::annotations(&field_declarator.node, &specifiers)
+                                .iter()
+                                .any(|a| a == "nullable")
+                            {
+                                nullable_members.push(id.node.name.clone());
+                            }
                         }
                     }
                 }
@@ -350,9 +745,24 @@ impl<'a> BorrowChecker<'a> {
             }
         }
 
+        self.apply_struct_spec(&struct_names, &mut struct_members, &mut nullable_members);
+
         // Adding this struct information under any of its possible names.
         // if !struct_members.is_empty() {
         for name in struct_names {
+            for member in &nullable_members {
+                self.nullable_fields.insert(format!("{name}.{member}"));
+            }
+            for member in &array_members {
+                self.array_fields.insert(format!("{name}.{member}"));
+            }
+            for (member, pointee) in &pointee_members {
+                self.pointee_structs
+                    .insert(format!("{name}.{member}"), pointee.clone());
+            }
+            if is_union {
+                self.unions.insert(name.clone());
+            }
             self.structs.insert(name, struct_members.clone());
         }
         // }
@@ -363,6 +773,7 @@ impl<'a> BorrowChecker<'a> {
         &mut self,
         declarator: &Node<Declarator>,
         parameter_declarations: &Vec<Node<ParameterDeclaration>>,
+        specifiers: &[Node<DeclarationSpecifier>],
     ) {
         let DeclaratorKind::Identifier(function_id) = &declarator.node.kind.node else {
             return;
@@ -380,9 +791,182 @@ impl<'a> BorrowChecker<'a> {
             );
             function_parameters.push(parameter_type);
         }
+
+        // A spec overrides the prototype, and what it says about the result replaces the lifetimes.
+        let spec = self.spec;
+        if let Some(function_spec) = spec.functions.get(&function_name) {
+            for (parameter_type, kind) in function_parameters
+                .iter_mut()
+                .zip(&function_spec.parameters)
+            {
+                *parameter_type = Self::parameter_type(parameter_type.clone(), kind);
+            }
+            if function_spec.returns.is_some() {
+                self.functions.insert(function_name, function_parameters);
+                return;
+            }
+        }
+        self.add_lifetimes(
+            &function_name,
+            declarator,
+            parameter_declarations,
+            specifiers,
+            &function_parameters,
+        );
         self.functions.insert(function_name, function_parameters);
     }
 
+    // Finds which parameters a returned pointer borrows from. Explicit lifetimes, written like Rust's as
+    // __attribute__((annotate("'a"))), tie it to every parameter with the same lifetime ('static or copy ties it to
+    // none). Without one, Rust's elision rule applies: with exactly one pointer parameter, the result borrows from it.
+    // A result annotated owned is new heap memory instead, like the result of malloc, and one annotated borrowed,
+    // borrowed_mut or nullable gives its callers that kind of reference.
+    fn add_lifetimes(
+        &mut self,
+        function_name: &str,
+        declarator: &Node<Declarator>,
+        parameter_declarations: &[Node<ParameterDeclaration>],
+        specifiers: &[Node<DeclarationSpecifier>],
+        parameter_types: &[VarType],
+    ) {
+        // The pointers of the return type come before the function's own derived declarator.
+        let returns_pointer = declarator
+            .node
+            .derived
+            .iter()
+            .take_while(|d| {
+                !matches!(
+                    d.node,
+                    DerivedDeclarator::Function(_) | DerivedDeclarator::KRFunction(_)
+                )
+            })
+            .any(|d| matches!(d.node, DerivedDeclarator::Pointer(_)));
+        if !returns_pointer {
+            return;
+        }
+
+        let return_annotations = Self::annotations(&declarator.node, specifiers);
+        let result_annotations: Vec<String> = return_annotations
+            .iter()
+            .filter(|a| ["borrowed", "borrowed_mut", "nullable"].contains(&a.as_str()))
+            .cloned()
+            .collect();
+        if !result_annotations.is_empty() {
+            self.result_annotations
+                .insert(function_name.to_string(), result_annotations);
+        }
+        if return_annotations.iter().any(|a| a == "owned") {
+            self.heap_functions
+                .allocators
+                .insert(function_name.to_string());
+            return;
+        }
+
+        let lifetime =
+            |annotations: Vec<String>| annotations.into_iter().find(|a| a.starts_with('\''));
+        let parameter_lifetimes: Vec<Option<String>> = parameter_declarations
+            .iter()
+            .filter_map(|parameter| {
+                let parameter_declarator = parameter.node.declarator.as_ref()?;
+                Some(lifetime(Self::annotations(
+                    &parameter_declarator.node,
+                    &parameter.node.specifiers,
+                )))
+            })
+            .collect();
+        let returns_from: Vec<usize> = match lifetime(return_annotations.clone()) {
+            _ if return_annotations.iter().any(|a| a == "copy") => Vec::new(),
+            Some(return_lifetime) => parameter_lifetimes
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| l.as_ref() == Some(&return_lifetime))
+                .map(|(index, _)| index)
+                .collect(),
+            None => {
+                let pointers: Vec<usize> = parameter_types
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| t.points_to().is_some())
+                    .map(|(index, _)| index)
+                    .collect();
+                if pointers.len() != 1 {
+                    return;
+                }
+                pointers
+            }
+        };
+        self.lifetimes
+            .insert(function_name.to_string(), returns_from);
+    }
+
+    // Records a function declared with _Noreturn or __attribute__((noreturn)).
+    pub fn add_if_noreturn(
+        &mut self,
+        declarator: &Node<Declarator>,
+        specifiers: &[Node<DeclarationSpecifier>],
+    ) {
+        let DeclaratorKind::Identifier(function_id) = &declarator.node.kind.node else {
+            return;
+        };
+        let is_noreturn_attribute = |extension: &Node<Extension>| match &extension.node {
+            Extension::Attribute(attribute) => {
+                attribute.name.node == "noreturn" || attribute.name.node == "__noreturn__"
+            }
+            _ => false,
+        };
+        let noreturn = specifiers.iter().any(|specifier| match &specifier.node {
+            DeclarationSpecifier::Function(function_specifier) => {
+                function_specifier.node == FunctionSpecifier::Noreturn
+            }
+            DeclarationSpecifier::Extension(extensions) => {
+                extensions.iter().any(is_noreturn_attribute)
+            }
+            _ => false,
+        }) || declarator.node.extensions.iter().any(is_noreturn_attribute);
+        if noreturn {
+            self.noreturn_functions
+                .insert(function_id.node.name.clone());
+        }
+    }
+
+    // The names given in __attribute__((annotate("name"))) on a declaration, its declarator or its pointers.
+    pub fn annotations(
+        declarator: &Declarator,
+        specifiers: &[Node<DeclarationSpecifier>],
+    ) -> Vec<String> {
+        let mut extensions: Vec<&Node<Extension>> = declarator.extensions.iter().collect();
+        for specifier in specifiers {
+            if let DeclarationSpecifier::Extension(specifier_extensions) = &specifier.node {
+                extensions.extend(specifier_extensions);
+            }
+        }
+        for derived_declarator in &declarator.derived {
+            if let DerivedDeclarator::Pointer(qualifiers) = &derived_declarator.node {
+                for qualifier in qualifiers {
+                    if let PointerQualifier::Extension(qualifier_extensions) = &qualifier.node {
+                        extensions.extend(qualifier_extensions);
+                    }
+                }
+            }
+        }
+
+        let mut annotations = Vec::new();
+        for extension in extensions {
+            let Extension::Attribute(attribute) = &extension.node else {
+                continue;
+            };
+            if attribute.name.node != "annotate" && attribute.name.node != "__annotate__" {
+                continue;
+            }
+            for argument in &attribute.arguments {
+                if let Expression::StringLiteral(literal) = &argument.node {
+                    annotations.push(literal.node.iter().map(|s| s.trim_matches('"')).collect());
+                }
+            }
+        }
+        annotations
+    }
+
     // Conversion function because struct member delcarations use a different set of specifiers than regular declarations.
     pub fn struct_specifier_to_declaration_specifier(
         &self,
@@ -399,12 +983,33 @@ impl<'a> BorrowChecker<'a> {
                     DeclarationSpecifier::TypeQualifier(tq.clone()),
                     specifier.span,
                 )),
-                _ => {}
+                SpecifierQualifier::Extension(extensions) => out.push(Node::new(
+                    DeclarationSpecifier::Extension(extensions.clone()),
+                    specifier.span,
+                )),
             }
         }
         return out;
     }
 
+    // Enum constants are Copy values, declared in the scope of their enum so they are known when used.
+    pub fn declare_enumerators(&mut self, specifiers: &[Node<DeclarationSpecifier>]) {
+        for specifier in specifiers {
+            let DeclarationSpecifier::TypeSpecifier(type_specifier) = &specifier.node else {
+                continue;
+            };
+            let TypeSpecifier::Enum(enum_type) = &type_specifier.node else {
+                continue;
+            };
+            let scope = self.scopes.len() - 1;
+            for enumerator in &enum_type.node.enumerators {
+                let name = enumerator.node.identifier.node.name.clone();
+                let variable = Variable::new(name.clone(), scope, VarType::Copy);
+                self.scopes[scope].insert(name, variable);
+            }
+        }
+    }
+
     // Adds the variable's name to the proper scope mapping.
     pub fn declare_variable(
         &mut self,
@@ -419,34 +1024,51 @@ impl<'a> BorrowChecker<'a> {
         let name = identifier.node.name.clone();
         let var_type = self.get_var_type(declarator, specifiers);
         let scope: usize = self.scopes.len() - 1;
-        self.scopes.last_mut().unwrap().insert(
-            name.clone(),
-            Variable::new(name.clone(), scope, var_type.clone()),
-        );
-        self.declare_unknown_global(&name, var_type, function_parameter);
+        let mut variable = Variable::new(name.clone(), scope, var_type.clone());
+        variable.nullable = Self::annotations(declarator, specifiers)
+            .iter()
+            .any(|a| a == "nullable");
+        // Array parameters are pointers to the caller's array.
+        variable.array = !function_parameter && Self::is_array_declarator(declarator);
+        if variable.array {
+            variable.dimensions = Self::array_dimensions(declarator);
+        }
+        variable.is_static = specifiers.iter().any(|specifier| {
+            matches!(
+                &specifier.node,
+                DeclarationSpecifier::StorageClass(storage_class)
+                    if storage_class.node == StorageClassSpecifier::Static
+            )
+        });
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.clone(), variable);
+        let pointee = self.previous_struct_name.clone();
+        self.declare_unknown_global(&name, var_type, &pointee, function_parameter);
     }
 
     // Adds a new global for a function parameter pointer or struct member pointer to point to (what it really points to is unknown).
+    // pointee is the struct the pointer was declared to point to, if any.
     pub fn declare_unknown_global(
         &mut self,
         name: &str,
         var_type: VarType,
+        pointee: &str,
         function_parameter: bool,
     ) {
         // Add the "?" unknown variable reference for pointers that are function arguments or struct members.
-        if (function_parameter || name.contains("."))
-            && matches!(var_type, VarType::ConstRef(_) | VarType::MutRef(_))
-        {
+        if (function_parameter || name.contains(".")) && var_type.points_to().is_some() {
             // Creates a global variable for the pointer to point to (used for pointer function parameters).
             let unknown_name = "?".to_string() + &name;
-            if !self.previous_struct_name.is_empty() {
+            if !pointee.is_empty() {
                 // Points to a struct, so it is assumed to point to a unique global of that type.
-                let unknown_var_type = VarType::Owner(self.previous_struct_name.clone(), true);
+                let unknown_var_type = VarType::Owner(pointee.to_string(), true);
                 self.scopes[0].insert(
                     unknown_name.to_string(),
                     Variable::new(unknown_name.to_string(), 0, unknown_var_type.clone()),
                 );
-                self.declare_unknown_global(&unknown_name, unknown_var_type, false)
+                self.declare_unknown_global(&unknown_name, unknown_var_type, "", false)
             } else {
                 // Does not point to a struct, so a shared global copy type is used.
                 self.scopes[0].insert(
@@ -458,14 +1080,8 @@ impl<'a> BorrowChecker<'a> {
 
             let new_var = self.name_to_mut_var(&name);
 
-            match &mut new_var.var_type {
-                VarType::ConstRef(points_to) => {
-                    points_to.insert(unknown_id.clone());
-                }
-                VarType::MutRef(points_to) => {
-                    points_to.insert(unknown_id.clone());
-                }
-                _ => {}
+            if let Some(points_to) = new_var.var_type.points_to_mut() {
+                points_to.insert(unknown_id.clone());
             }
 
             let new_id = new_var.id.clone();
@@ -473,7 +1089,7 @@ impl<'a> BorrowChecker<'a> {
             let unknown_var = self.id_to_mut_var(&unknown_id);
             match new_type {
                 VarType::ConstRef(_) => unknown_var.const_refs.insert(new_id),
-                VarType::MutRef(_) => unknown_var.mut_refs.insert(new_id),
+                VarType::MutRef(_) | VarType::OwnedPtr(_, _) => unknown_var.mut_refs.insert(new_id),
                 _ => false,
             };
         }
@@ -487,6 +1103,26 @@ impl<'a> BorrowChecker<'a> {
         self.mute_member_expression = false;
     }
 
+    // The variable an expression names, like p, s.x or (int *) p.
+    pub fn expression_name(&mut self, expression: &Node<Expression>) -> Option<String> {
+        match &expression.node {
+            Expression::Cast(cast_expression) => {
+                self.expression_name(&cast_expression.node.expression)
+            }
+            Expression::Identifier(identifier) => Some(identifier.node.name.clone()),
+            Expression::Member(member_expression) => {
+                self.get_member_expression_identifier(member_expression);
+                Some(self.member_identifier.clone())
+            }
+            // Every element of an array is the array itself.
+            Expression::BinaryOperator(boe) if boe.node.operator.node == BinaryOperator::Index => {
+                let array_name = self.expression_name(&boe.node.lhs)?;
+                self.is_array(&array_name).then_some(array_name)
+            }
+            _ => None,
+        }
+    }
+
     // Given an expression, sets its ownership if it's an owner type.
     pub fn set_expression_ownership(
         &mut self,
@@ -494,12 +1130,41 @@ impl<'a> BorrowChecker<'a> {
         is_valid: bool,
         span: &span::Span,
     ) {
+        if Self::is_indexed(expression) {
+            // Assigning to an element does not change who owns the array, but moving out of one does.
+            if is_valid && self.write_to_element(expression) {
+                return;
+            }
+            visit::visit_expression(self, &expression.node, &expression.span);
+            if !is_valid {
+                self.move_out_of_index(expression, span);
+            }
+            return;
+        }
         match &expression.node {
+            // Arrays decay to a pointer to their first element instead of being moved.
+            Expression::Identifier(name) if self.is_array(&name.node.name) => {
+                self.visit_expression(&expression.node, &expression.span);
+            }
             Expression::Identifier(name) => {
                 self.set_all_ownership(name.node.name.clone(), is_valid, span);
             }
             Expression::Member(member_expression) => {
                 self.get_member_expression_identifier(member_expression);
+                if self.is_array(&self.member_identifier.clone()) {
+                    self.visit_expression(&expression.node, &expression.span);
+                    return;
+                }
+                // Writing a union member overwrites the others, and reading an owner out of one reinterprets them.
+                if is_valid {
+                    self.overwrite_union_members(&self.member_identifier.clone());
+                } else {
+                    self.read_union_member(&self.member_identifier.clone(), span);
+                }
+                if !is_valid && Self::is_behind_pointer(expression) {
+                    self.move_out_of_pointer(self.member_identifier.clone(), span);
+                    return;
+                }
                 self.set_all_ownership(self.member_identifier.clone(), is_valid, span);
             }
             _ => visit::visit_expression(self, &expression.node, &expression.span),
@@ -509,11 +1174,17 @@ impl<'a> BorrowChecker<'a> {
     // Prints the error message for an owner type being used without ownership.
     pub fn announce_no_ownership(&mut self, name: String, &span: &span::Span) {
         let variable = self.name_to_var(&name);
-        if matches!(variable.var_type, VarType::Owner(_, false)) {
-            let (location, _) = get_location_for_offset(self.src, span.start);
-            println!(
-                "ERROR: Use of moved value '{}' on line {}.",
-                name, location.line
+        if matches!(
+            variable.var_type,
+            VarType::Owner(_, false) | VarType::OwnedPtr(_, false)
+        ) {
+            let killed_at = variable.killed_at;
+            self.report(
+                ErrorCode::UseAfterMove,
+                &span,
+                format!("Use of moved value '{name}'"),
+                Self::moved_label(killed_at, &span),
+                vec![name],
             );
         }
     }
@@ -528,6 +1199,10 @@ impl<'a> BorrowChecker<'a> {
                     // Assume any of the possible references to this variable are all active.
                     variable.const_refs.extend(v.const_refs.clone());
                     variable.mut_refs.extend(v.mut_refs.clone());
+                    variable.killed_at = v.killed_at.or(variable.killed_at);
+                    variable.borrowed_at = v.borrowed_at.or(variable.borrowed_at);
+                    variable.allocated_at = v.allocated_at.or(variable.allocated_at);
+                    variable.freed_at = v.freed_at.or(variable.freed_at);
 
                     // Type-specific merging.
                     match &v.var_type {
@@ -537,11 +1212,37 @@ impl<'a> BorrowChecker<'a> {
                                 variable.var_type = VarType::Owner(type_name.clone(), *o1 && o2);
                             }
                         }
-                        VarType::ConstRef(points_to1) | VarType::MutRef(points_to1) => {
-                            if let VarType::ConstRef(points_to2) = &mut variable.var_type {
-                                // Might be pointing to anything it was pointing to in either scope.
-                                // Pointing to an out-of-scope variable handled separately.
+                        VarType::OwnedPtr(points_to1, o1) => match &mut variable.var_type {
+                            VarType::OwnedPtr(points_to2, o2) => {
+                                // Joined like an Owner, and like a reference for what it points to.
                                 points_to2.extend(points_to1.clone());
+                                *o2 = *o1 && *o2;
+                            }
+                            VarType::ConstRef(points_to2) | VarType::MutRef(points_to2) => {
+                                // Owns what it points to on one path (like p = malloc(...) on one branch). A
+                                // reference was never moved, so only the owning side decides the ownership.
+                                let mut points_to = std::mem::take(points_to2);
+                                points_to.extend(points_to1.clone());
+                                variable.var_type = VarType::OwnedPtr(points_to, *o1);
+                            }
+                            _ => {}
+                        },
+                        VarType::ConstRef(points_to1) | VarType::MutRef(points_to1) => {
+                            // Might be pointing to anything it was pointing to in either scope, mutably if it was
+                            // mutable in either. Pointing to an out-of-scope variable handled separately.
+                            let mutable = matches!(v.var_type, VarType::MutRef(_));
+                            match &mut variable.var_type {
+                                VarType::ConstRef(points_to2) if mutable => {
+                                    let mut points_to = std::mem::take(points_to2);
+                                    points_to.extend(points_to1.clone());
+                                    variable.var_type = VarType::MutRef(points_to);
+                                }
+                                VarType::ConstRef(points_to2)
+                                | VarType::MutRef(points_to2)
+                                | VarType::OwnedPtr(points_to2, _) => {
+                                    points_to2.extend(points_to1.clone());
+                                }
+                                _ => {}
                             }
                         }
                         VarType::Copy => {}
@@ -566,7 +1267,9 @@ impl<'a> BorrowChecker<'a> {
                     let inner = s
                         .iter()
                         .map(|(k, v)| {
-                            if let VarType::Owner(_, has_ownership) = v.var_type {
+                            if let VarType::Owner(_, has_ownership)
+                            | VarType::OwnedPtr(_, has_ownership) = v.var_type
+                            {
                                 format!("{k}:{}", has_ownership as i32)
                             } else {
                                 k.to_string()
@@ -584,163 +1287,205 @@ impl<'a> BorrowChecker<'a> {
 
 // Functions for the borrowing (reference) rules.
 impl<'a> BorrowChecker<'a> {
-    // Remove a reference from all the variables it points to.
+    // Remove a reference from all the variables it points to. An array of pointers keeps what its other elements
+    // point to.
     pub fn clear_points_to(&mut self, id: &Id) {
+        if self.id_to_var(id).array {
+            return;
+        }
         match &self.id_to_var(id).var_type {
             VarType::ConstRef(points_to) => {
-                let ids = points_to.clone();
-                for id in ids.iter() {
-                    let pointed_to = self.id_to_mut_var(id);
+                let ids: Vec<Id> = points_to
+                    .iter()
+                    .filter(|var_id| self.is_in_scope(var_id))
+                    .cloned()
+                    .collect();
+                for var_id in ids.iter() {
+                    let pointed_to = self.id_to_mut_var(var_id);
                     pointed_to.const_refs.remove(id);
                 }
             }
-            VarType::MutRef(points_to) => {
-                let ids = points_to.clone();
-                for id in ids.iter() {
-                    let pointed_to = self.id_to_mut_var(id);
+            VarType::MutRef(points_to) | VarType::OwnedPtr(points_to, _) => {
+                let ids: Vec<Id> = points_to
+                    .iter()
+                    .filter(|var_id| self.is_in_scope(var_id))
+                    .cloned()
+                    .collect();
+                for var_id in ids.iter() {
+                    let pointed_to = self.id_to_mut_var(var_id);
                     pointed_to.mut_refs.remove(id);
                 }
             }
             _ => {}
         }
 
-        match &mut self.id_to_mut_var(id).var_type {
-            VarType::ConstRef(points_to) => {
-                points_to.clear();
-            }
-            VarType::MutRef(points_to) => {
-                points_to.clear();
-            }
-            _ => {}
+        if let Some(points_to) = self.id_to_mut_var(id).var_type.points_to_mut() {
+            points_to.clear();
         }
     }
 
     // Adds all of source's pointed to variables to desination's points_to set, and updates the corresponding pointed_to variables.
     pub fn copy_points_to(&mut self, destination: &Id, source: &Id, span: &span::Span) {
-        // For error prints.
-        let (location, _) = get_location_for_offset(self.src, span.start);
-
         let source_var_type = self.id_to_var(source).var_type.clone();
+
+        // Out-of-scope targets are still copied, so using the destination reports them, but there is no variable left to update.
+        let in_scope_targets: Vec<Id> = match source_var_type.points_to() {
+            Some(points_to) => points_to
+                .iter()
+                .filter(|id| self.is_in_scope(id))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
         let destination_var = self.id_to_mut_var(destination);
+        destination_var.borrowed_at = Some(*span);
         match (&mut destination_var.var_type, &source_var_type) {
+            // Assigning an owning pointer moves it, so the destination becomes the owner.
+            (dest_type, VarType::OwnedPtr(source_points_to, _)) => {
+                *dest_type = VarType::OwnedPtr(source_points_to.clone(), true);
+                for var_id in &in_scope_targets {
+                    let var = self.id_to_mut_var(var_id);
+                    var.mut_refs.remove(source);
+                    var.mut_refs.insert(destination.clone());
+                }
+            }
             (VarType::ConstRef(dest_points_to), VarType::ConstRef(source_points_to)) => {
                 dest_points_to.extend(source_points_to.clone());
-                for var_id in source_points_to {
-                    let var = self.id_to_mut_var(&var_id);
+                for var_id in &in_scope_targets {
+                    let var = self.id_to_mut_var(var_id);
                     var.const_refs.insert(destination.clone());
                 }
             }
-            (VarType::MutRef(dest_points_to), VarType::MutRef(source_points_to)) => {
+            (
+                VarType::MutRef(dest_points_to) | VarType::OwnedPtr(dest_points_to, _),
+                VarType::MutRef(source_points_to),
+            ) => {
                 dest_points_to.extend(source_points_to.clone());
-                for var_id in source_points_to {
-                    let var = self.id_to_mut_var(&var_id);
+                for var_id in &in_scope_targets {
+                    let var = self.id_to_mut_var(var_id);
                     var.mut_refs.remove(source);
                     var.mut_refs.insert(destination.clone());
                 }
             }
             (VarType::ConstRef(dest_points_to), VarType::MutRef(source_points_to)) => {
-                println!(
-                    "ERROR: Moving mutable reference '{}' to const reference '{}' on line {}.",
-                    source.name, destination.name, location.line
-                );
                 dest_points_to.extend(source_points_to.clone());
-                for var_id in source_points_to {
-                    let var = self.id_to_mut_var(&var_id);
+                for var_id in &in_scope_targets {
+                    let var = self.id_to_mut_var(var_id);
                     var.mut_refs.remove(source);
                     var.const_refs.insert(destination.clone());
                 }
+                self.report(
+                    ErrorCode::RefKindMismatch,
+                    span,
+                    format!(
+                        "Moving mutable reference '{}' to const reference '{}'",
+                        source.name, destination.name
+                    ),
+                    vec![],
+                    vec![source.name.clone(), destination.name.clone()],
+                );
             }
             (VarType::MutRef(dest_points_to), VarType::ConstRef(source_points_to)) => {
-                println!(
-                    "ERROR: Moving const reference '{}' to mutable reference '{}' on line {}.",
-                    source.name, destination.name, location.line
-                );
                 dest_points_to.extend(source_points_to.clone());
-                for var_id in source_points_to {
-                    let var = self.id_to_mut_var(&var_id);
+                for var_id in &in_scope_targets {
+                    let var = self.id_to_mut_var(var_id);
                     var.const_refs.remove(source);
                     var.mut_refs.insert(destination.clone());
                 }
+                self.report(
+                    ErrorCode::RefKindMismatch,
+                    span,
+                    format!(
+                        "Moving const reference '{}' to mutable reference '{}'",
+                        source.name, destination.name
+                    ),
+                    vec![],
+                    vec![source.name.clone(), destination.name.clone()],
+                );
             }
             _ => {}
         }
     }
 
+    // The other variables that share memory with a struct piece: the structs it is part of and the fields under it.
+    // For s.x, they are s and s.x.y, but not s.y, so borrows of different fields do not conflict.
+    pub fn overlapping_pieces(&self, var_id: &Id) -> Vec<Id> {
+        let is_under = |name: &str, parent: &str| {
+            name.len() > parent.len()
+                && name.starts_with(parent)
+                && name[parent.len()..].starts_with('.')
+        };
+        self.scopes[var_id.scope]
+            .keys()
+            .filter(|name| is_under(name, &var_id.name) || is_under(&var_id.name, name))
+            .map(|name| Id {
+                name: name.clone(),
+                scope: var_id.scope,
+            })
+            .collect()
+    }
+
+    // Borrowing a variable invalidates the borrows that conflict with it, of the variable or any piece it overlaps.
+    pub fn invalidate_borrows(&mut self, var_id: &Id, mutable: bool) {
+        let mut pieces = self.overlapping_pieces(var_id);
+        pieces.push(var_id.clone());
+        for piece in pieces {
+            let var = self.id_to_mut_var(&piece);
+            if mutable {
+                var.const_refs.clear();
+            }
+            var.mut_refs.clear();
+        }
+    }
+
     pub fn add_const_ref(&mut self, var_id: &Id, ref_id: &Id) {
+        self.invalidate_borrows(var_id, false);
         let var = self.id_to_mut_var(var_id);
-        var.mut_refs.clear();
         var.const_refs.insert(ref_id.clone());
     }
 
     pub fn add_mut_ref(&mut self, var_id: &Id, ref_id: &Id) {
+        self.invalidate_borrows(var_id, true);
         let var = self.id_to_mut_var(var_id);
-        var.const_refs.clear();
-        var.mut_refs.clear();
         var.mut_refs.insert(ref_id.clone());
     }
 
     // Handles p=&x cases.
-    pub fn reference_from_address(&mut self, lhs: String, rhs: &Expression) {
-        match &rhs {
-            Expression::Identifier(operand) => {
-                let rhs_id = self.get_id(&operand.node.name);
-                let lhs_id = self.get_id(&lhs);
-
-                match &self.id_to_var(&lhs_id).var_type {
-                    VarType::ConstRef(_) => {
-                        self.clear_points_to(&lhs_id);
-                        self.add_const_ref(&rhs_id, &lhs_id)
-                    }
-                    VarType::MutRef(_) => {
-                        self.clear_points_to(&lhs_id);
-                        self.add_mut_ref(&rhs_id, &lhs_id)
-                    }
-                    _ => {}
-                }
+    pub fn reference_from_address(
+        &mut self,
+        lhs: String,
+        rhs: &Node<Expression>,
+        span: &span::Span,
+    ) {
+        let Some(var_id) = self.borrowed_variable(rhs) else {
+            return;
+        };
+        let ref_id = self.get_id(&lhs);
 
-                match &mut self.id_to_mut_var(&lhs_id).var_type {
-                    VarType::ConstRef(points_to) => {
-                        points_to.insert(rhs_id.clone());
-                    }
-                    VarType::MutRef(points_to) => {
-                        points_to.insert(rhs_id.clone());
-                    }
-                    _ => {}
-                }
+        match &self.id_to_var(&ref_id).var_type {
+            VarType::ConstRef(_) => {
+                self.clear_points_to(&ref_id);
+                self.add_const_ref(&var_id, &ref_id)
             }
-            Expression::Member(operand) => {
-                self.get_member_expression_identifier(operand);
-
-                // Borrowing any piece of a struct borrows the entire struct.
-                let parent_name =
-                    &self.member_identifier[0..self.member_identifier.find(".").unwrap()];
-                let var_id = self.get_id(parent_name);
-                let ref_id = self.get_id(&lhs);
-
-                match &self.id_to_var(&ref_id).var_type {
-                    VarType::ConstRef(_) => {
-                        self.clear_points_to(&ref_id);
-                        self.add_const_ref(&var_id, &ref_id)
-                    }
-                    VarType::MutRef(_) => {
-                        self.clear_points_to(&ref_id);
-                        self.add_mut_ref(&var_id, &ref_id)
-                    }
-                    _ => {}
-                }
-
-                match &mut self.id_to_mut_var(&ref_id).var_type {
-                    VarType::ConstRef(points_to) => {
-                        points_to.insert(var_id.clone());
-                    }
-                    VarType::MutRef(points_to) => {
-                        points_to.insert(var_id.clone());
-                    }
-                    _ => {}
-                }
+            VarType::MutRef(_) | VarType::OwnedPtr(_, _) => {
+                self.clear_points_to(&ref_id);
+                self.add_mut_ref(&var_id, &ref_id)
             }
             _ => {}
         }
+
+        let reference = self.id_to_mut_var(&ref_id);
+        reference.borrowed_at = Some(*span);
+        if let Some(points_to) = reference.var_type.points_to_mut() {
+            points_to.insert(var_id.clone());
+        }
+    }
+
+    // f(&x): passing a reference to a mutable parameter (or one whose type is unknown) makes all previous mut and const
+    // references invalid. Passing it to a const parameter makes all previous mut references invalid.
+    pub fn borrow_argument(&mut self, var_id: &Id, parameter: Option<&VarType>) {
+        let mutable = matches!(parameter, None | Some(VarType::MutRef(_)));
+        self.invalidate_borrows(var_id, mutable);
     }
 
     // Handles p2=p1 (where both of those are pointers).
@@ -755,30 +1500,31 @@ impl<'a> BorrowChecker<'a> {
 
     // Given a LHS variable name and a RHS expression, computes all reference-related changes (p=&x, p2=p1, etc).
     pub fn add_reference(&mut self, lhs: String, rhs: &Node<Expression>, span: &span::Span) {
+        if let Some(call_expression) = self.allocation(rhs) {
+            self.allocate(lhs, &call_expression.span);
+            return;
+        }
+        // Pointer arithmetic keeps the provenance of the pointer it starts from.
+        if let Some(base) = self.arithmetic_base(rhs) {
+            self.add_derived_reference(lhs, base, span);
+            return;
+        }
         match &rhs.node {
             Expression::UnaryOperator(uoe) => match uoe.node.operator.node {
                 UnaryOperator::Address => {
-                    self.reference_from_address(lhs, &uoe.node.operand.node);
+                    self.reference_from_address(lhs, &uoe.node.operand, span);
                 }
                 UnaryOperator::Indirection => {
                     // For preventing non-copy moves from behind references.
                     self.visit_unary_operator_expression(&uoe.node, &uoe.span);
-                    let dereferenced_var = self.name_to_var(&self.dereference_name.clone());
-                    match dereferenced_var.var_type {
-                        VarType::Copy | VarType::ConstRef(_) => {
-                            self.reference_assignment(lhs, self.dereference_name.clone(), span);
-                        }
-                        _ => {
-                            let (location, _) = get_location_for_offset(self.src, span.start);
-                            println!(
-                                "ERROR: Cannot move non-Copy type '{}' from behind a reference on line {}.",
-                                self.dereference_name, location.line
-                            );
-                        }
-                    }
+                    self.reference_from_dereference(lhs, span);
                 }
                 _ => {}
             },
+            // Arrays decay to a pointer to their first element, so p = arr is p = &arr[0].
+            Expression::Identifier(rhs_identifier) if self.is_array(&rhs_identifier.node.name) => {
+                self.reference_from_address(lhs, rhs, span);
+            }
             Expression::Identifier(rhs_identifier) => {
                 self.reference_assignment(lhs, rhs_identifier.node.name.clone(), span);
             }
@@ -787,20 +1533,201 @@ impl<'a> BorrowChecker<'a> {
                 if self.member_identifier.is_empty() {
                     self.get_member_expression_identifier(member_expression);
                 }
+                if self.is_array(&self.member_identifier.clone()) && !Self::is_indexed(rhs) {
+                    self.reference_from_address(lhs, rhs, span);
+                    return;
+                }
                 self.reference_assignment(lhs, self.member_identifier.clone(), span);
             }
+            // m[i] of int m[2][3] is an array too, which decays like m does.
+            Expression::BinaryOperator(_) if self.is_sub_array(rhs) => {
+                self.reference_from_address(lhs, rhs, span);
+            }
+            Expression::BinaryOperator(boe) if boe.node.operator.node == BinaryOperator::Index => {
+                if let Some(name) = self.expression_name(rhs) {
+                    self.reference_assignment(lhs, name, span);
+                } else if let Some(pointer) = self.base_pointer_name(&boe.node.lhs) {
+                    // p[i] is *(p + i).
+                    self.dereference_name = self.dereference_target(&pointer);
+                    self.reference_from_dereference(lhs, span);
+                }
+            }
+            Expression::Cast(cast_expression) => {
+                self.add_reference(lhs, &cast_expression.node.expression, span);
+            }
+            Expression::Call(call_expression) => {
+                self.borrow_from_arguments(&lhs, call_expression, span);
+            }
+            Expression::Constant(_) => {
+                // p = 0 (or NULL after preprocessing) leaves p pointing to nothing.
+                let lhs_id = self.get_id(&lhs);
+                self.clear_points_to(&lhs_id);
+            }
             _ => {}
         }
     }
 
+    // x = *p, once p has been visited: what p points to is copied into x, but non-Copy values cannot be moved out.
+    pub fn reference_from_dereference(&mut self, lhs: String, span: &span::Span) {
+        if self.dereference_name.is_empty() {
+            return;
+        }
+        let dereferenced_var = self.name_to_var(&self.dereference_name.clone());
+        let is_heap = dereferenced_var.allocated_at.is_some();
+        match dereferenced_var.var_type {
+            VarType::Copy | VarType::ConstRef(_) => {
+                self.reference_assignment(lhs, self.dereference_name.clone(), span);
+            }
+            _ if is_heap => {}
+            _ => {
+                let name = self.dereference_name.clone();
+                self.report(
+                    ErrorCode::MoveBehindReference,
+                    span,
+                    format!("Cannot move non-Copy type '{name}' from behind a reference"),
+                    vec![],
+                    vec![name],
+                );
+            }
+        }
+    }
+
+    // The variable that &x borrows. A struct field is borrowed on its own, like s.x for &s.x, but borrowing any
+    // element of an array borrows all of it, like arr for &arr[i].x.
+    pub fn borrowed_variable(&mut self, operand: &Node<Expression>) -> Option<Id> {
+        let name = self.expression_name(operand)?;
+        let mut path = String::new();
+        for piece in name.split('.') {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(piece);
+            if !piece.is_empty() && self.root_variable(&path).is_some() && self.is_array(&path) {
+                break;
+            }
+        }
+        // Only for its side effect: a field that has not been used yet gets its variable created, so it has an Id.
+        self.name_to_var(&path);
+        Some(self.get_id(&path))
+    }
+
+    // The variables a returned value points to: x for return &x, and everything p points to for return p.
+    pub fn returned_targets(&mut self, expression: &Node<Expression>) -> Vec<Id> {
+        // Returning an array returns a pointer to it.
+        if let Some(array_id) = self.decayed_array(expression) {
+            return vec![array_id];
+        }
+        match &expression.node {
+            Expression::UnaryOperator(uoe) if uoe.node.operator.node == UnaryOperator::Address => {
+                self.borrowed_variable(&uoe.node.operand)
+                    .into_iter()
+                    .collect()
+            }
+            _ => match self.expression_name(expression) {
+                Some(name) => match self.name_to_var(&name).var_type.points_to() {
+                    Some(points_to) => points_to.iter().cloned().collect(),
+                    None => Vec::new(),
+                },
+                None => Vec::new(),
+            },
+        }
+    }
+
+    // Error messages for returning a reference to a variable that is dropped when the function returns. Only globals
+    // (including heap memory and what pointer parameters point to) outlive the function. Parameters are local copies.
+    pub fn announce_returned_locals(
+        &mut self,
+        expression: &Node<Expression>,
+        targets: &[Id],
+        span: &span::Span,
+    ) {
+        let reference_name = match &expression.node {
+            Expression::UnaryOperator(_) => None,
+            _ => self.expression_name(expression),
+        };
+        for target in targets {
+            // What a pointer parameter p points to is the unknown global ?p.
+            let parameter = target
+                .name
+                .strip_prefix('?')
+                .and_then(|n| n.split('.').next())
+                .filter(|p| self.unreturnable_parameters.contains(*p));
+            if let Some(parameter) = parameter {
+                let function = self.current_function.clone();
+                self.report(
+                    ErrorCode::LifetimeMismatch,
+                    span,
+                    format!(
+                        "Returning a reference into parameter '{parameter}', which the lifetimes of '{function}' do not allow"
+                    ),
+                    vec![],
+                    vec![parameter.to_string()],
+                );
+            }
+
+            // References to variables that are already out of scope are reported when they are used, and globals and
+            // static locals outlive the function.
+            if target.scope == 0 || !self.is_in_scope(target) || self.id_to_var(target).is_static {
+                continue;
+            }
+            match &reference_name {
+                Some(name) => {
+                    let borrowed_at = self.name_to_var(name).borrowed_at;
+                    self.report(
+                        ErrorCode::ReturnLocalRef,
+                        span,
+                        format!(
+                            "Returning '{name}', a reference to local variable '{}'",
+                            target.name
+                        ),
+                        Self::label(borrowed_at, "borrow created here"),
+                        vec![name.clone(), target.name.clone()],
+                    );
+                }
+                None => self.report(
+                    ErrorCode::ReturnLocalRef,
+                    span,
+                    format!("Returning a reference to local variable '{}'", target.name),
+                    vec![],
+                    vec![target.name.clone()],
+                ),
+            }
+        }
+    }
+
+    // return x moves x to the caller.
+    pub fn move_returned_owner(&mut self, expression: &Node<Expression>, span: &span::Span) {
+        if !matches!(
+            expression.node,
+            Expression::Identifier(_) | Expression::Member(_)
+        ) {
+            return;
+        }
+        let Some(name) = self.expression_name(expression) else {
+            return;
+        };
+        if matches!(self.name_to_var(&name).var_type, VarType::Owner(_, _)) {
+            self.set_expression_ownership(expression, false, span);
+        }
+    }
+
     pub fn announce_if_non_copy_behind_reference(&mut self, name: String, span: &span::Span) {
+        if self.dereference_name.is_empty() {
+            return;
+        }
         let dereferenced_var = self.name_to_var(&self.dereference_name.clone());
+        if dereferenced_var.allocated_at.is_some() {
+            return;
+        }
         match dereferenced_var.var_type {
-            VarType::Owner(_, _) | VarType::MutRef(_) => {
-                let (location, _) = get_location_for_offset(self.src, span.start);
-                println!(
-                    "ERROR: Cannot move non-Copy type '{}' from behind a reference on line {}.",
-                    self.dereference_name, location.line
+            VarType::Owner(_, _) | VarType::MutRef(_) | VarType::OwnedPtr(_, _) => {
+                let name = self.dereference_name.clone();
+                self.report(
+                    ErrorCode::MoveBehindReference,
+                    span,
+                    format!("Cannot move non-Copy type '{name}' from behind a reference"),
+                    vec![],
+                    vec![name],
                 );
             }
             _ => {}
@@ -810,61 +1737,117 @@ impl<'a> BorrowChecker<'a> {
     // Error messages for the use of a reference who's pointed-to variable does not recognize the reference (reference since invalidated).
     pub fn announce_invalid_reference(&mut self, name: String, &span: &span::Span) {
         let ref_id = self.get_id(&name);
-        let reference = &self.name_to_var(&name).var_type;
-        match reference {
+        let reference = self.name_to_var(&name);
+        let borrowed_at = reference.borrowed_at;
+        // Nullable pointers may point to nothing.
+        let nullable = reference.nullable;
+        match &reference.var_type {
             VarType::ConstRef(points_to) => {
                 let ids = points_to.clone();
-                if ids.is_empty() {
-                    let (location, _) = get_location_for_offset(self.src, span.start);
-                    println!(
-                        "ERROR: using '{}', a constant reference to no value, on line '{}'",
-                        ref_id.name, location.line
+                if ids.is_empty() && !nullable {
+                    self.report(
+                        ErrorCode::InvalidConstRef,
+                        &span,
+                        format!("Using '{}', a constant reference to no value", ref_id.name),
+                        Self::label(borrowed_at, "borrow created here"),
+                        vec![ref_id.name.clone()],
                     );
                 } else {
                     for var_id in ids {
-                        if var_id.scope >= self.scopes.len() {
-                            let (location, _) = get_location_for_offset(self.src, span.start);
-                            println!(
-                                "ERROR: using '{}', a constant reference to out-of-scope variable '{}', on line {}",
-                                ref_id.name, var_id.name, location.line
+                        if !self.is_in_scope(&var_id) {
+                            self.report(
+                                ErrorCode::DanglingRef,
+                                &span,
+                                format!(
+                                    "Using '{}', a constant reference to out-of-scope variable '{}'",
+                                    ref_id.name, var_id.name
+                                ),
+                                Self::label(borrowed_at, "borrow created here"),
+                                vec![ref_id.name.clone(), var_id.name.clone()],
+                            );
+                        } else if self.is_freed(&var_id) {
+                            let freed_at = self.id_to_var(&var_id).freed_at;
+                            self.report(
+                                ErrorCode::UseAfterFree,
+                                &span,
+                                format!(
+                                    "Using '{}', a constant reference to freed memory '{}'",
+                                    ref_id.name, var_id.name
+                                ),
+                                Self::label(freed_at, "memory freed here"),
+                                vec![ref_id.name.clone(), var_id.name.clone()],
                             );
                         } else {
                             let var = self.id_to_var(&var_id);
                             if !var.const_refs.contains(&ref_id) {
-                                let (location, _) = get_location_for_offset(self.src, span.start);
-                                println!(
-                                "ERROR: using '{}', an invalid constant reference to '{}', on line {}",
-                                ref_id.name, var_id.name, location.line
-                            );
+                                self.report(
+                                    ErrorCode::InvalidConstRef,
+                                    &span,
+                                    format!(
+                                        "Using '{}', an invalid constant reference to '{}'",
+                                        ref_id.name, var_id.name
+                                    ),
+                                    Self::label(borrowed_at, "borrow created here"),
+                                    vec![ref_id.name.clone(), var_id.name.clone()],
+                                );
                             }
                         }
                     }
                 }
             }
-            VarType::MutRef(points_to) => {
+            VarType::MutRef(points_to) | VarType::OwnedPtr(points_to, true) => {
+                // Owning pointers borrow what they point to mutably, so they are checked the same way.
+                let (a_kind, kind) = match reference.var_type {
+                    VarType::OwnedPtr(_, _) => ("an owning pointer", "owning pointer"),
+                    _ => ("a mutable reference", "mutable reference"),
+                };
                 let ids = points_to.clone();
-                if ids.is_empty() {
-                    let (location, _) = get_location_for_offset(self.src, span.start);
-                    println!(
-                        "ERROR: using '{}', a mutable reference to no value, on line {}",
-                        ref_id.name, location.line
+                if ids.is_empty() && !nullable {
+                    self.report(
+                        ErrorCode::InvalidMutRef,
+                        &span,
+                        format!("Using '{}', {a_kind} to no value", ref_id.name),
+                        Self::label(borrowed_at, "borrow created here"),
+                        vec![ref_id.name.clone()],
                     );
                 } else {
                     for var_id in ids {
-                        if var_id.scope >= self.scopes.len() {
-                            let (location, _) = get_location_for_offset(self.src, span.start);
-                            println!(
-                                "ERROR: using '{}', a mutable reference to out-of-scope variable '{}', on line {}",
-                                ref_id.name, var_id.name, location.line
+                        if !self.is_in_scope(&var_id) {
+                            self.report(
+                                ErrorCode::DanglingRef,
+                                &span,
+                                format!(
+                                    "Using '{}', {a_kind} to out-of-scope variable '{}'",
+                                    ref_id.name, var_id.name
+                                ),
+                                Self::label(borrowed_at, "borrow created here"),
+                                vec![ref_id.name.clone(), var_id.name.clone()],
+                            );
+                        } else if self.is_freed(&var_id) {
+                            let freed_at = self.id_to_var(&var_id).freed_at;
+                            self.report(
+                                ErrorCode::UseAfterFree,
+                                &span,
+                                format!(
+                                    "Using '{}', {a_kind} to freed memory '{}'",
+                                    ref_id.name, var_id.name
+                                ),
+                                Self::label(freed_at, "memory freed here"),
+                                vec![ref_id.name.clone(), var_id.name.clone()],
                             );
                         } else {
                             let var = self.id_to_var(&var_id);
                             if !var.mut_refs.contains(&ref_id) {
-                                let (location, _) = get_location_for_offset(self.src, span.start);
-                                println!(
-                                "ERROR: using '{}', an invalid mutable reference to '{}', on line {}",
-                                ref_id.name, var_id.name, location.line
-                            );
+                                self.report(
+                                    ErrorCode::InvalidMutRef,
+                                    &span,
+                                    format!(
+                                        "Using '{}', an invalid {kind} to '{}'",
+                                        ref_id.name, var_id.name
+                                    ),
+                                    Self::label(borrowed_at, "borrow created here"),
+                                    vec![ref_id.name.clone(), var_id.name.clone()],
+                                );
                             }
                         }
                     }
@@ -874,6 +1857,30 @@ impl<'a> BorrowChecker<'a> {
         }
     }
 
+    // The name of the variable a reference points to, or nothing if it points to nothing that is still in scope.
+    // Only the first possibility (by name) is followed.
+    pub fn dereference_target(&mut self, name: &str) -> String {
+        self.dereference_targets(name)
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+    }
+
+    // The names of every in-scope variable a reference may point to, in order.
+    pub fn dereference_targets(&mut self, name: &str) -> Vec<String> {
+        let var_type = self.name_to_var(name).var_type.clone();
+        let mut targets: Vec<String> = match var_type.points_to() {
+            Some(points_to) => points_to
+                .iter()
+                .filter(|id| self.is_in_scope(id))
+                .map(|id| id.name.clone())
+                .collect(),
+            None => Vec::new(),
+        };
+        targets.sort();
+        targets
+    }
+
     // Prints the set of references. {const ref},{mut ref}'->variable. Mutable references have the '
     pub fn print_references(&self, &span: &span::Span) {
         let (location, _) = get_location_for_offset(self.src, span.start);
@@ -922,6 +1929,17 @@ impl<'a> BorrowChecker<'a> {
                                         .collect::<String>()
                                 )
                             }
+                            VarType::OwnedPtr(points_to, has_ownership) => {
+                                format!(
+                                    "{k}:{}'->{{{}}}",
+                                    *has_ownership as i32,
+                                    points_to
+                                        .iter()
+                                        .map(|id| id.name.clone())
+                                        .intersperse(", ".to_string())
+                                        .collect::<String>()
+                                )
+                            }
                         })
                         .intersperse("; ".to_string());
                     format!("{{{}}}", inner.collect::<String>())
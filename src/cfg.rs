@@ -0,0 +1,395 @@
+use lang_c::ast::*;
+use lang_c::span::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+// One step of a basic block. The checker runs each one with the visitor rules in ast_traversal.rs.
+pub enum CfgItem<'ast> {
+    Declaration(&'ast Node<Declaration>),
+    Expression(&'ast Node<Expression>),
+    // The value a function returns, which is given to the caller.
+    Return(&'ast Node<Expression>),
+}
+
+impl<'ast> CfgItem<'ast> {
+    pub fn span(&self) -> &'ast Span {
+        match self {
+            CfgItem::Declaration(declaration) => &declaration.span,
+            CfgItem::Expression(expression) | CfgItem::Return(expression) => &expression.span,
+        }
+    }
+}
+
+pub struct BasicBlock<'ast> {
+    pub items: Vec<CfgItem<'ast>>,
+    // The number of scopes open in this block, including the global scope.
+    pub depth: usize,
+    pub successors: Vec<usize>,
+    // The closing brace of the block statement whose scope ends right before this block, if any.
+    pub closes_scope: Option<Span>,
+}
+
+// Control flow graph of one function body. Blocks are numbered in the order they appear in the source.
+pub struct Cfg<'ast> {
+    pub blocks: Vec<BasicBlock<'ast>>,
+    pub entry: usize,
+    pub exit: usize,
+}
+
+impl<'ast> Cfg<'ast> {
+    // Builds the graph of a function body, whose parameters are already declared in the scope at depth.
+    // Calls to any of the noreturn functions end their path.
+    pub fn new(
+        function_definition: &'ast FunctionDefinition,
+        depth: usize,
+        noreturn_functions: &HashSet<String>,
+    ) -> Self {
+        let mut builder = CfgBuilder {
+            blocks: Vec::new(),
+            current: 0,
+            depth,
+            jump_targets: Vec::new(),
+            returns: Vec::new(),
+            labels: HashMap::new(),
+            gotos: Vec::new(),
+            noreturn_functions,
+        };
+        let entry = builder.new_block();
+        builder.current = entry;
+
+        // The function body shares the scope of the parameters.
+        match &function_definition.statement.node {
+            Statement::Compound(block_items) => builder.block_items(block_items),
+            _ => builder.statement(&function_definition.statement),
+        }
+
+        // Falling off the end of the body and every return lead to the exit.
+        let exit = builder.next_block();
+        builder.blocks[exit].closes_scope = Some(closing_brace(&function_definition.statement));
+        for block in std::mem::take(&mut builder.returns) {
+            builder.edge(block, exit);
+        }
+
+        // Labels can come after their gotos, so gotos are connected once the whole body is built.
+        for (label, block) in std::mem::take(&mut builder.gotos) {
+            if let Some(&target) = builder.labels.get(&label) {
+                builder.edge(block, target);
+            }
+        }
+        Cfg {
+            blocks: builder.blocks,
+            entry,
+            exit,
+        }
+    }
+}
+
+// The last character of a statement, which is the closing brace of a block.
+fn closing_brace(statement: &Node<Statement>) -> Span {
+    Span::span(statement.span.end - 1, statement.span.end)
+}
+
+#[derive(PartialEq)]
+enum JumpKind {
+    Loop,
+    Switch,
+}
+
+// The blocks that end in a break or continue, waiting for the block they jump to to be built.
+struct JumpTargets {
+    kind: JumpKind,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+    // The blocks that start at each case label of a switch.
+    cases: Vec<usize>,
+    has_default: bool,
+}
+
+impl JumpTargets {
+    fn new(kind: JumpKind) -> Self {
+        JumpTargets {
+            kind,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            cases: Vec::new(),
+            has_default: false,
+        }
+    }
+}
+
+struct CfgBuilder<'ast, 'n> {
+    blocks: Vec<BasicBlock<'ast>>,
+    // The block new items are added to.
+    current: usize,
+    depth: usize,
+
+    // The enclosing loops and switches, innermost last.
+    jump_targets: Vec<JumpTargets>,
+    // The blocks that end in a return.
+    returns: Vec<usize>,
+    // The block each label starts, and the blocks that end in a goto with the label they jump to.
+    labels: HashMap<String, usize>,
+    gotos: Vec<(String, usize)>,
+    noreturn_functions: &'n HashSet<String>,
+}
+
+impl<'ast, 'n> CfgBuilder<'ast, 'n> {
+    fn new_block(&mut self) -> usize {
+        self.blocks.push(BasicBlock {
+            items: Vec::new(),
+            depth: self.depth,
+            successors: Vec::new(),
+            closes_scope: None,
+        });
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        if !self.blocks[from].successors.contains(&to) {
+            self.blocks[from].successors.push(to);
+        }
+    }
+
+    // Starts a new block that control falls through to from the current one.
+    fn next_block(&mut self) -> usize {
+        let block = self.new_block();
+        self.edge(self.current, block);
+        self.current = block;
+        block
+    }
+
+    fn item(&mut self, item: CfgItem<'ast>) {
+        self.blocks[self.current].items.push(item);
+    }
+
+    // Ends the current path. Anything after it goes in a new block that nothing leads to, so it is never checked.
+    fn end_path(&mut self) -> usize {
+        let block = self.current;
+        self.current = self.new_block();
+        block
+    }
+
+    // Runs a loop body with its own break and continue targets. Returns the blocks that continue.
+    fn loop_body(&mut self, body: &'ast Node<Statement>) -> JumpTargets {
+        self.jump_targets.push(JumpTargets::new(JumpKind::Loop));
+        self.statement(body);
+        self.jump_targets.pop().unwrap()
+    }
+
+    // Ends the path at a break or continue, to be connected once the loop or switch they jump within is built.
+    fn loop_jump(&mut self, statement: &Statement) {
+        let target_index = match statement {
+            // Breaks leave the innermost loop or switch, continues skip switches.
+            Statement::Break => self.jump_targets.len().checked_sub(1),
+            _ => self
+                .jump_targets
+                .iter()
+                .rposition(|t| t.kind == JumpKind::Loop),
+        };
+        let Some(target_index) = target_index else {
+            return;
+        };
+
+        let block = self.end_path();
+        let targets = &mut self.jump_targets[target_index];
+        match statement {
+            Statement::Break => targets.breaks.push(block),
+            _ => targets.continues.push(block),
+        }
+    }
+
+    fn is_noreturn_call(&self, expression: &Expression) -> bool {
+        let Expression::Call(call_expression) = expression else {
+            return false;
+        };
+        let Expression::Identifier(function_id) = &call_expression.node.callee.node else {
+            return false;
+        };
+        self.noreturn_functions.contains(&function_id.node.name)
+    }
+
+    fn block_items(&mut self, block_items: &'ast [Node<BlockItem>]) {
+        for block_item in block_items {
+            match &block_item.node {
+                BlockItem::Declaration(declaration) => self.item(CfgItem::Declaration(declaration)),
+                BlockItem::Statement(statement) => self.statement(statement),
+                BlockItem::StaticAssert(_) => {}
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &'ast Node<Statement>) {
+        match &statement.node {
+            Statement::Compound(block_items) => {
+                // Blocks get their own scope, which is dropped when control leaves them.
+                self.depth += 1;
+                self.next_block();
+                self.block_items(block_items);
+                self.depth -= 1;
+                let block = self.next_block();
+                self.blocks[block].closes_scope = Some(closing_brace(statement));
+            }
+            Statement::Expression(Some(expression)) => {
+                self.item(CfgItem::Expression(expression));
+                if self.is_noreturn_call(&expression.node) {
+                    self.end_path();
+                }
+            }
+            Statement::If(if_statement) => {
+                let if_statement = &if_statement.node;
+                self.item(CfgItem::Expression(&if_statement.condition));
+                let condition = self.current;
+
+                self.next_block();
+                self.statement(&if_statement.then_statement);
+                let then_end = self.current;
+
+                let else_end = match &if_statement.else_statement {
+                    Some(else_statement) => {
+                        self.current = condition;
+                        self.next_block();
+                        self.statement(else_statement);
+                        self.current
+                    }
+                    None => condition,
+                };
+
+                let join = self.new_block();
+                self.edge(then_end, join);
+                self.edge(else_end, join);
+                self.current = join;
+            }
+            Statement::While(while_statement) => {
+                let while_statement = &while_statement.node;
+                let head = self.next_block();
+                self.item(CfgItem::Expression(&while_statement.expression));
+
+                self.next_block();
+                let jumps = self.loop_body(&while_statement.statement);
+                self.edge(self.current, head);
+                for block in jumps.continues {
+                    self.edge(block, head);
+                }
+
+                self.current = head;
+                let exit = self.next_block();
+                for block in jumps.breaks {
+                    self.edge(block, exit);
+                }
+            }
+            Statement::DoWhile(do_while_statement) => {
+                let do_while_statement = &do_while_statement.node;
+                let body = self.next_block();
+                let jumps = self.loop_body(&do_while_statement.statement);
+
+                let condition = self.next_block();
+                self.item(CfgItem::Expression(&do_while_statement.expression));
+                self.edge(self.current, body);
+                for block in jumps.continues {
+                    self.edge(block, condition);
+                }
+
+                let exit = self.next_block();
+                for block in jumps.breaks {
+                    self.edge(block, exit);
+                }
+            }
+            Statement::For(for_statement) => {
+                let for_statement = &for_statement.node;
+
+                // Variables declared in the initializer only exist inside the loop.
+                self.depth += 1;
+                self.next_block();
+                match &for_statement.initializer.node {
+                    ForInitializer::Expression(expression) => {
+                        self.item(CfgItem::Expression(expression));
+                    }
+                    ForInitializer::Declaration(declaration) => {
+                        self.item(CfgItem::Declaration(declaration));
+                    }
+                    _ => {}
+                }
+
+                let head = self.next_block();
+                if let Some(condition) = &for_statement.condition {
+                    self.item(CfgItem::Expression(condition));
+                }
+
+                self.next_block();
+                let jumps = self.loop_body(&for_statement.statement);
+                let step = self.next_block();
+                if let Some(step) = &for_statement.step {
+                    self.item(CfgItem::Expression(step));
+                }
+                self.edge(self.current, head);
+                for block in jumps.continues {
+                    self.edge(block, step);
+                }
+
+                self.current = head;
+                self.depth -= 1;
+                let exit = self.next_block();
+                for block in jumps.breaks {
+                    self.edge(block, exit);
+                }
+            }
+            Statement::Labeled(labeled_statement) => {
+                let labeled_statement = &labeled_statement.node;
+                let switch_index = self
+                    .jump_targets
+                    .iter()
+                    .rposition(|t| t.kind == JumpKind::Switch);
+                if let Label::Identifier(label) = &labeled_statement.label.node {
+                    // A label is reached by falling through to it and by every goto to it.
+                    let block = self.next_block();
+                    self.labels.insert(label.node.name.clone(), block);
+                } else if let Some(switch_index) = switch_index {
+                    // Each case starts from the state before the switch, joined with the previous case falling through.
+                    let case = self.next_block();
+                    let targets = &mut self.jump_targets[switch_index];
+                    targets.cases.push(case);
+                    if let Label::Default = labeled_statement.label.node {
+                        targets.has_default = true;
+                    }
+                }
+                self.statement(&labeled_statement.statement);
+            }
+            Statement::Switch(switch_statement) => {
+                let switch_statement = &switch_statement.node;
+                self.item(CfgItem::Expression(&switch_statement.expression));
+                // Control only enters the body at a case label.
+                let dispatch = self.end_path();
+
+                self.jump_targets.push(JumpTargets::new(JumpKind::Switch));
+                self.statement(&switch_statement.statement);
+                let targets = self.jump_targets.pop().unwrap();
+
+                // The last case falls through to the end, breaks jump to it, and so does no case matching.
+                let exit = self.next_block();
+                for case in targets.cases {
+                    self.edge(dispatch, case);
+                }
+                for block in targets.breaks {
+                    self.edge(block, exit);
+                }
+                if !targets.has_default {
+                    self.edge(dispatch, exit);
+                }
+            }
+            Statement::Return(expression) => {
+                if let Some(expression) = expression {
+                    self.item(CfgItem::Return(expression));
+                }
+                let block = self.end_path();
+                self.returns.push(block);
+            }
+            Statement::Break | Statement::Continue => self.loop_jump(&statement.node),
+            Statement::Goto(label) => {
+                let block = self.end_path();
+                self.gotos.push((label.node.name.clone(), block));
+            }
+            // Empty statements and inline assembly do not change any state.
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use serde_json::Value;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+// One translation unit from a compile_commands.json, with the flags that change how it is preprocessed.
+pub struct CompileCommand {
+    pub file: String,
+    pub cpp_flags: Vec<String>,
+}
+
+// Preprocessor flags that take a path, either joined (-Idir) or as the next argument (-I dir).
+const PATH_FLAGS: [&str; 4] = ["-I", "-isystem", "-iquote", "-include"];
+// Preprocessor flags that take any other value.
+const VALUE_FLAGS: [&str; 2] = ["-D", "-U"];
+
+// Reads a compilation database. Relative directories are relative to the database itself.
+pub fn read(path: &str) -> Result<Vec<CompileCommand>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read '{path}': {e}"))?;
+    let database: Value =
+        serde_json::from_str(&text).map_err(|e| format!("'{path}' is not valid JSON: {e}"))?;
+    let Value::Array(entries) = database else {
+        return Err(format!("'{path}' is not a list of compile commands"));
+    };
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+
+    let mut commands: Vec<CompileCommand> = Vec::new();
+    for entry in &entries {
+        let field = |name: &str| entry.get(name).and_then(Value::as_str);
+        let (Some(directory), Some(file)) = (field("directory"), field("file")) else {
+            return Err(format!("an entry in '{path}' has no directory or file"));
+        };
+        let directory = base.join(directory);
+
+        let arguments = match (entry.get("arguments"), field("command")) {
+            (Some(Value::Array(arguments)), _) => arguments
+                .iter()
+                .filter_map(|a| a.as_str().map(str::to_string))
+                .collect(),
+            (_, Some(command)) => split_command(command),
+            _ => return Err(format!("the entry for '{file}' in '{path}' has no command")),
+        };
+
+        // A file compiled more than once (for several targets) is only checked the first time.
+        let file = display_path(&directory.join(file));
+        if commands.iter().any(|c| c.file == file) {
+            continue;
+        }
+        commands.push(CompileCommand {
+            cpp_flags: cpp_flags(&arguments, &directory),
+            file,
+        });
+    }
+    Ok(commands)
+}
+
+// The include and macro flags of a compiler command line. Everything else (optimization, warnings, output files)
+// does not matter to the checker.
+fn cpp_flags(arguments: &[String], directory: &Path) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        if argument.starts_with("-std=") {
+            flags.push(argument.clone());
+            continue;
+        }
+        let Some(flag) = PATH_FLAGS
+            .iter()
+            .chain(VALUE_FLAGS.iter())
+            .find(|flag| argument.starts_with(*flag))
+        else {
+            continue;
+        };
+        let value = if argument.len() > flag.len() {
+            argument[flag.len()..].to_string()
+        } else {
+            match arguments.next() {
+                Some(value) => value.clone(),
+                None => break,
+            }
+        };
+
+        if PATH_FLAGS.contains(flag) {
+            flags.push(flag.to_string());
+            flags.push(display_path(&directory.join(value)));
+        } else {
+            flags.push(format!("{flag}{value}"));
+        }
+    }
+    flags
+}
+
+// Splits a shell command into its arguments, keeping quoted strings together.
+fn split_command(command: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut argument = String::new();
+    let mut in_argument = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => argument.push(c),
+            ('\\', _) => argument.extend(chars.next()),
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => argument.push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                in_argument = true;
+                continue;
+            }
+            (c, None) if c.is_whitespace() => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut argument));
+                }
+                in_argument = false;
+                continue;
+            }
+            _ => argument.push(c),
+        }
+        in_argument = true;
+    }
+    if in_argument {
+        arguments.push(argument);
+    }
+    arguments
+}
+
+// Paths are printed without the ./ that joining to a "." directory adds.
+fn display_path(path: &Path) -> String {
+    let path: PathBuf = path
+        .components()
+        .filter(|component| component != &Component::CurDir)
+        .collect();
+    path.to_string_lossy().into_owned()
+}
@@ -0,0 +1,163 @@
+use crate::cfg::*;
+use crate::variable::*;
+use crate::BorrowChecker;
+use crate::PrintType;
+use lang_c::ast::FunctionDefinition;
+use lang_c::visit::Visit;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+// The ownership and reference state at one point of a function: the scopes, from global to innermost.
+pub type State = Vec<HashMap<String, Variable>>;
+
+// A block whose state has not settled after this many visits is assumed to have reached its fixed point.
+const MAX_BLOCK_VISITS: usize = 100;
+
+// Functions for running the checker over a function's control flow graph.
+impl<'a> BorrowChecker<'a> {
+    // Checks a function body, starting from the global scope. Leaves self.scopes at the state at the end of it.
+    pub fn check_function(&mut self, function_definition: &FunctionDefinition) {
+        self.return_points_to.clear();
+
+        // Only the parameters the signature ties the result to can be returned into.
+        self.unreturnable_parameters.clear();
+        if let Some(returns_from) = self.lifetimes.get(&self.current_function) {
+            for (index, name) in Self::parameter_names(function_definition)
+                .into_iter()
+                .enumerate()
+            {
+                if let Some(name) = name.filter(|_| !returns_from.contains(&index)) {
+                    self.unreturnable_parameters.insert(name);
+                }
+            }
+        }
+
+        // Functions add the new scope early so it can include all their parameters.
+        self.scopes.push(HashMap::new());
+
+        // Copied from visit::visit_function_definition to replace the declarator visit with only visiting the derived declarators (the function name is not a variable).
+        // The specifiers are the return type, whose typedef name is not a variable either.
+        for derived_declarator in &function_definition.declarator.node.derived {
+            self.visit_derived_declarator(&derived_declarator.node, &derived_declarator.span)
+        }
+        for declaration in &function_definition.declarations {
+            self.visit_declaration(&declaration.node, &declaration.span);
+        }
+
+        // The body is checked over its control flow graph.
+        let cfg = Cfg::new(
+            function_definition,
+            self.scopes.len(),
+            &self.noreturn_functions,
+        );
+        self.check_cfg(&cfg);
+    }
+
+    // Finds the state at the start of every block with a worklist, then runs each reachable block once more from
+    // its final state to report errors. Leaves self.scopes at the state at the end of the function.
+    pub fn check_cfg<'ast>(&mut self, cfg: &Cfg<'ast>) {
+        let mut in_states: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+        let mut visits = vec![0; cfg.blocks.len()];
+        in_states[cfg.entry] = Some(self.scopes.clone());
+
+        let silent = self.silent;
+        self.silent = true;
+        let mut worklist = VecDeque::from([cfg.entry]);
+        while let Some(block) = worklist.pop_front() {
+            visits[block] += 1;
+            self.scopes = in_states[block].clone().unwrap();
+            self.transfer(&cfg.blocks[block]);
+
+            for &successor in &cfg.blocks[block].successors {
+                let mut out_state = self.scopes.clone();
+                Self::fit_depth(&mut out_state, cfg.blocks[successor].depth);
+                if self.join_into(&mut in_states[successor], out_state)
+                    && visits[successor] < MAX_BLOCK_VISITS
+                    && !worklist.contains(&successor)
+                {
+                    worklist.push_back(successor);
+                }
+            }
+        }
+        self.silent = silent;
+
+        // Reporting pass, in source order.
+        let mut exit_state = None;
+        for (block, in_state) in in_states.iter().enumerate() {
+            if let Some(in_state) = in_state {
+                self.scopes = in_state.clone();
+                self.transfer(&cfg.blocks[block]);
+                if block == cfg.exit {
+                    exit_state = Some(self.scopes.clone());
+                }
+            }
+        }
+        if let Some(exit_state) = exit_state {
+            self.scopes = exit_state;
+            self.report_leaks(true, cfg.blocks[cfg.exit].closes_scope);
+        }
+    }
+
+    // Runs every item of a block, using the existing rules as the transfer function.
+    // Memory can be leaked by any item, or by the scope its pointer was declared in ending before the block.
+    fn transfer<'ast>(&mut self, block: &BasicBlock<'ast>) {
+        self.report_leaks(false, block.closes_scope);
+        for item in &block.items {
+            self.print_sets(item.span());
+            match item {
+                CfgItem::Declaration(declaration) => {
+                    self.visit_declaration(&declaration.node, &declaration.span)
+                }
+                CfgItem::Expression(expression) => {
+                    self.visit_expression(&expression.node, &expression.span)
+                }
+                CfgItem::Return(expression) => {
+                    // The returned value belongs to the caller now, so it must outlive the function.
+                    self.visit_expression(&expression.node, &expression.span);
+                    let targets = self.returned_targets(expression);
+                    self.announce_returned_locals(expression, &targets, &expression.span);
+                    self.return_points_to.extend(targets);
+                    self.move_returned_owner(expression, &expression.span);
+                    self.give_away(expression, &expression.span);
+                }
+            }
+            self.report_leaks(false, Some(*item.span()));
+            self.print_sets(item.span());
+        }
+    }
+
+    // Prints the requested sets, except while a fixed point is being found.
+    fn print_sets(&self, span: &lang_c::span::Span) {
+        if self.silent {
+            return;
+        }
+        match self.set_prints {
+            PrintType::Ownership => self.print_ownership(span),
+            PrintType::Reference => self.print_references(span),
+            PrintType::ErrorOnly => {}
+        }
+    }
+
+    // Joins a new incoming state into a block's state. Returns whether the block's state changed.
+    fn join_into(&mut self, in_state: &mut Option<State>, incoming: State) -> bool {
+        let Some(existing) = in_state else {
+            *in_state = Some(incoming);
+            return true;
+        };
+
+        let scopes = std::mem::replace(&mut self.scopes, existing.clone());
+        self.merge_scopes(&incoming);
+        let joined = std::mem::replace(&mut self.scopes, scopes);
+        let changed = joined != *existing;
+        *existing = joined;
+        changed
+    }
+
+    // Opens or drops scopes so a state matches the number of scopes open in the block it flows into.
+    fn fit_depth(state: &mut State, depth: usize) {
+        state.truncate(depth);
+        while state.len() < depth {
+            state.push(HashMap::new());
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use lang_c::loc::*;
+use lang_c::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+// Every kind of problem the checker can report. The codes must stay stable so they can be filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UseAfterMove,
+    InvalidConstRef,
+    InvalidMutRef,
+    DanglingRef,
+    MoveBehindReference,
+    RefKindMismatch,
+    UseAfterFree,
+    DoubleFree,
+    MemoryLeak,
+    ReturnLocalRef,
+    LifetimeMismatch,
+    UnknownFunction,
+    MoveOutOfArray,
+    UnionReinterpretation,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 14] = [
+        ErrorCode::UseAfterMove,
+        ErrorCode::InvalidConstRef,
+        ErrorCode::InvalidMutRef,
+        ErrorCode::DanglingRef,
+        ErrorCode::MoveBehindReference,
+        ErrorCode::RefKindMismatch,
+        ErrorCode::UseAfterFree,
+        ErrorCode::DoubleFree,
+        ErrorCode::MemoryLeak,
+        ErrorCode::ReturnLocalRef,
+        ErrorCode::LifetimeMismatch,
+        ErrorCode::UnknownFunction,
+        ErrorCode::MoveOutOfArray,
+        ErrorCode::UnionReinterpretation,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCode::UseAfterMove => "E001",
+            ErrorCode::InvalidConstRef => "E002",
+            ErrorCode::InvalidMutRef => "E003",
+            ErrorCode::DanglingRef => "E004",
+            ErrorCode::MoveBehindReference => "E005",
+            ErrorCode::RefKindMismatch => "E006",
+            ErrorCode::UseAfterFree => "E007",
+            ErrorCode::DoubleFree => "E008",
+            ErrorCode::MemoryLeak => "E009",
+            ErrorCode::ReturnLocalRef => "E010",
+            ErrorCode::LifetimeMismatch => "E011",
+            ErrorCode::UnknownFunction => "E012",
+            ErrorCode::MoveOutOfArray => "E013",
+            ErrorCode::UnionReinterpretation => "E014",
+        }
+    }
+
+    // Rule name for machine-readable output.
+    pub fn rule_id(&self) -> &'static str {
+        match self {
+            ErrorCode::UseAfterMove => "use-after-move",
+            ErrorCode::InvalidConstRef => "invalid-const-ref",
+            ErrorCode::InvalidMutRef => "invalid-mut-ref",
+            ErrorCode::DanglingRef => "dangling-ref",
+            ErrorCode::MoveBehindReference => "move-behind-reference",
+            ErrorCode::RefKindMismatch => "ref-kind-mismatch",
+            ErrorCode::UseAfterFree => "use-after-free",
+            ErrorCode::DoubleFree => "double-free",
+            ErrorCode::MemoryLeak => "memory-leak",
+            ErrorCode::ReturnLocalRef => "return-local-ref",
+            ErrorCode::LifetimeMismatch => "lifetime-mismatch",
+            ErrorCode::UnknownFunction => "unknown-function",
+            ErrorCode::MoveOutOfArray => "move-out-of-array",
+            ErrorCode::UnionReinterpretation => "union-reinterpretation",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::UseAfterMove => "Use of a moved value",
+            ErrorCode::InvalidConstRef => "Use of an invalidated constant reference",
+            ErrorCode::InvalidMutRef => "Use of an invalidated mutable reference",
+            ErrorCode::DanglingRef => "Use of a reference to an out-of-scope variable",
+            ErrorCode::MoveBehindReference => "Move of a non-Copy value from behind a reference",
+            ErrorCode::RefKindMismatch => "Assignment between constant and mutable references",
+            ErrorCode::UseAfterFree => "Use of a pointer to freed heap memory",
+            ErrorCode::DoubleFree => "Freeing heap memory that was already freed",
+            ErrorCode::MemoryLeak => "Heap memory that is never freed",
+            ErrorCode::ReturnLocalRef => "Returning a reference to a local variable",
+            ErrorCode::LifetimeMismatch => "Returning a reference its lifetimes do not allow",
+            ErrorCode::UnknownFunction => "Call to a function that was never declared",
+            ErrorCode::MoveOutOfArray => "Move of a non-Copy element out of an array",
+            ErrorCode::UnionReinterpretation => "Read of a non-Copy member of a union",
+        }
+    }
+
+    // Leaks are only warnings, since the memory might be freed somewhere the checker cannot see. Union reads are too,
+    // since the checker does not know which member was written last.
+    pub fn severity(&self) -> Severity {
+        match self {
+            ErrorCode::MemoryLeak | ErrorCode::UnionReinterpretation => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+// A secondary location that explains the diagnostic, like where a value was moved.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub severity: Severity,
+    // The expression the problem was found in.
+    pub span: Span,
+    pub message: String,
+    pub labels: Vec<Label>,
+    // The variables involved, in the order they appear in the message.
+    pub variables: Vec<String>,
+    // The function definition being checked when the problem was found.
+    pub function: String,
+}
+
+// Where an offset into the preprocessed source is in the original file.
+pub struct SourceLocation<'a> {
+    pub file: &'a str,
+    pub line: usize,
+    pub column: usize,
+    // The full text of the line the offset is on.
+    pub text: &'a str,
+}
+
+impl<'a> SourceLocation<'a> {
+    pub fn new(src: &'a str, offset: usize) -> Self {
+        let (location, _) = get_location_for_offset(src, offset);
+        let line_start = src[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = src[offset..].find('\n').map_or(src.len(), |i| offset + i);
+        SourceLocation {
+            file: location.file,
+            line: location.line,
+            column: offset - line_start + 1,
+            text: &src[line_start..line_end],
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn location<'a>(&self, src: &'a str) -> SourceLocation<'a> {
+        SourceLocation::new(src, self.span.start)
+    }
+
+    // rustc-style rendering: the message, its location, and the source lines of the span and labels underlined.
+    pub fn render(&self, src: &str) -> String {
+        let severity = self.severity.name();
+
+        // The primary span is underlined with ^, the labels with -.
+        let mut annotations: Vec<(SourceLocation, usize, char, &str)> = Vec::new();
+        annotations.push((self.location(src), self.span.end - self.span.start, '^', ""));
+        for label in &self.labels {
+            let label_location = SourceLocation::new(src, label.span.start);
+            annotations.push((
+                label_location,
+                label.span.end - label.span.start,
+                '-',
+                &label.message,
+            ));
+        }
+        annotations.sort_by_key(|(l, _, _, _)| (l.line, l.column));
+
+        let gutter = annotations
+            .iter()
+            .map(|(l, _, _, _)| l.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let blank = " ".repeat(gutter);
+        let location = self.location(src);
+
+        let mut out = format!(
+            "{severity}[{}]: {}\n{blank}--> {}:{}:{}\n{blank} |\n",
+            self.code.code(),
+            self.message,
+            location.file,
+            location.line,
+            location.column
+        );
+        let mut previous_line = None;
+        for (location, length, underline, message) in &annotations {
+            if previous_line.is_some_and(|line| line + 1 < location.line) {
+                out += &format!("{blank} |\n");
+            }
+            if previous_line != Some(location.line) {
+                out += &format!("{:>gutter$} | {}\n", location.line, location.text);
+            }
+            previous_line = Some(location.line);
+
+            // Tabs are kept so the underline lines up with the source text. Spans past the end of the line are cut off.
+            let indent: String = location.text[..location.column - 1]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let length = (*length)
+                .min(location.text.len() + 1 - location.column)
+                .max(1);
+            let marks = underline.to_string().repeat(length);
+            out += format!("{blank} | {indent}{marks} {message}").trim_end();
+            out += "\n";
+        }
+        out
+    }
+}
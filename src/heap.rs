@@ -0,0 +1,227 @@
+use crate::diagnostic::*;
+use crate::variable::*;
+use crate::BorrowChecker;
+use lang_c::ast::*;
+use lang_c::span::*;
+use lang_c::visit::Visit;
+use std::collections::HashSet;
+
+// Heap functions from the C library and the Linux kernel that are always known.
+const ALLOCATORS: [&str; 9] = [
+    "malloc",
+    "calloc",
+    "aligned_alloc",
+    "kmalloc",
+    "kzalloc",
+    "kcalloc",
+    "kmalloc_array",
+    "kvmalloc",
+    "kvzalloc",
+];
+const DEALLOCATORS: [&str; 3] = ["free", "kfree", "kvfree"];
+const REALLOCATORS: [&str; 2] = ["realloc", "krealloc"];
+
+// The functions that allocate and free heap memory.
+pub struct HeapFunctions {
+    pub allocators: HashSet<String>,
+    pub deallocators: HashSet<String>,
+    // Reallocators free the memory their first argument points to and allocate their result.
+    pub reallocators: HashSet<String>,
+}
+
+impl HeapFunctions {
+    // The default heap functions plus the ones given by the user.
+    pub fn new(allocators: &[String], deallocators: &[String], reallocators: &[String]) -> Self {
+        let with_defaults = |defaults: &[&str], extra: &[String]| {
+            defaults
+                .iter()
+                .map(|f| f.to_string())
+                .chain(extra.iter().cloned())
+                .collect()
+        };
+        HeapFunctions {
+            allocators: with_defaults(&ALLOCATORS, allocators),
+            deallocators: with_defaults(&DEALLOCATORS, deallocators),
+            reallocators: with_defaults(&REALLOCATORS, reallocators),
+        }
+    }
+}
+
+// Functions for heap memory. Each allocation is a global Owner, owned by the pointer it is assigned to and
+// named after that pointer and where it was allocated: *p@12:13. Freeing it moves out of it.
+impl<'a> BorrowChecker<'a> {
+    // The allocator or reallocator call an expression is, ignoring casts like (int *) malloc(4). Functions defined in
+    // the file that return new heap memory count as allocators.
+    pub fn allocation<'e>(
+        &self,
+        expression: &'e Node<Expression>,
+    ) -> Option<&'e Node<CallExpression>> {
+        match &expression.node {
+            Expression::Cast(cast_expression) => self.allocation(&cast_expression.node.expression),
+            Expression::Call(call_expression) => {
+                let Expression::Identifier(function_id) = &call_expression.node.callee.node else {
+                    return None;
+                };
+                let name = &function_id.node.name;
+                (self.heap_functions.allocators.contains(name)
+                    || self.heap_functions.reallocators.contains(name)
+                    || self
+                        .summaries
+                        .get(name)
+                        .is_some_and(|s| s.returns_allocation))
+                .then_some(call_expression)
+            }
+            _ => None,
+        }
+    }
+
+    // p = malloc(...): p becomes an owning pointer to a new heap Owner.
+    pub fn allocate(&mut self, lhs: String, span: &Span) {
+        let lhs_id = self.get_id(&lhs);
+        if self.id_to_var(&lhs_id).var_type.points_to().is_none() {
+            return;
+        }
+        self.clear_points_to(&lhs_id);
+        // The memory from an earlier run of this allocation (in a loop) is leaked if nothing points to it anymore.
+        self.report_leaks(false, Some(*span));
+
+        let location = SourceLocation::new(self.src, span.start);
+        let heap_name = format!("*{lhs}@{}:{}", location.line, location.column);
+        let heap_id = Id {
+            name: heap_name.clone(),
+            scope: 0,
+        };
+        // The type of heap memory is not known, so moving out of it with *p is allowed like for Copy types.
+        let mut heap = Variable::new(heap_name.clone(), 0, VarType::Owner(String::new(), true));
+        heap.allocated_at = Some(*span);
+
+        heap.mut_refs.insert(lhs_id.clone());
+        let pointer = self.id_to_mut_var(&lhs_id);
+        pointer.borrowed_at = Some(*span);
+        // An array of pointers also keeps the memory its other elements own.
+        let mut owned = HashSet::from([heap_id]);
+        if pointer.array {
+            owned.extend(pointer.var_type.points_to().into_iter().flatten().cloned());
+        }
+        pointer.var_type = VarType::OwnedPtr(owned, true);
+        self.scopes[0].insert(heap_name, heap);
+    }
+
+    // free(p): moves out of everything p points to, which invalidates every reference to it.
+    pub fn free(&mut self, pointer: &Node<Expression>, span: &Span) {
+        let Some(name) = self.expression_name(pointer) else {
+            self.visit_expression(&pointer.node, &pointer.span);
+            return;
+        };
+        if name == "NULL" {
+            return;
+        }
+        // free(arr[i]) does not say which element's memory was freed, so the memory of every element is only given
+        // away (it is not leaked, but using the other elements is still allowed).
+        if Self::is_indexed(pointer) {
+            self.visit_expression(&pointer.node, &pointer.span);
+            self.give_away(pointer, span);
+            return;
+        }
+
+        for target in self.in_scope_targets(&name) {
+            let variable = self.id_to_mut_var(&target);
+            let VarType::Owner(type_name, owned) = variable.var_type.clone() else {
+                // Memory the checker knows nothing about (like ?p) is only marked, so the summary of the function
+                // knows that its parameter was freed.
+                variable.killed_at = Some(*span);
+                continue;
+            };
+            if owned {
+                variable.var_type = VarType::Owner(type_name, false);
+                variable.killed_at = Some(*span);
+                variable.freed_at = Some(*span);
+                variable.const_refs.clear();
+                variable.mut_refs.clear();
+            } else {
+                let freed_at = variable.freed_at.or(variable.killed_at);
+                self.report(
+                    ErrorCode::DoubleFree,
+                    span,
+                    format!("Freeing '{name}', which points to memory that was already freed"),
+                    Self::label(freed_at, "memory freed here"),
+                    vec![name.clone()],
+                );
+            }
+        }
+    }
+
+    // The variables a pointer points to that are still in scope.
+    pub fn in_scope_targets(&mut self, name: &str) -> Vec<Id> {
+        let mut targets: Vec<Id> = match self.name_to_var(name).var_type.points_to() {
+            Some(points_to) => points_to.iter().cloned().collect(),
+            None => Vec::new(),
+        };
+        targets.retain(|id| self.is_in_scope(id));
+        targets.sort_by(|a, b| a.name.cmp(&b.name));
+        targets
+    }
+
+    // Whether a variable is memory that was freed (and not revived since).
+    pub fn is_freed(&self, id: &Id) -> bool {
+        let variable = self.id_to_var(id);
+        variable.freed_at.is_some() && matches!(variable.var_type, VarType::Owner(_, false))
+    }
+
+    // return p, or passing p to a parameter that owns it: the heap memory p points to belongs to someone else now,
+    // so it is not leaked.
+    pub fn give_away(&mut self, expression: &Node<Expression>, span: &Span) {
+        let Some(name) = self.expression_name(expression) else {
+            return;
+        };
+        for target in self.in_scope_targets(&name) {
+            let variable = self.id_to_mut_var(&target);
+            if variable.allocated_at.is_none() {
+                continue;
+            }
+            if let VarType::Owner(type_name, true) = variable.var_type.clone() {
+                variable.var_type = VarType::Owner(type_name, false);
+                variable.killed_at = Some(*span);
+            }
+        }
+    }
+
+    // Warns about heap memory that is still owned but that no variable points to anymore. At the end of a function,
+    // only globals and struct members (which might live on in the caller) still point to anything. lost_at is where
+    // the last pointer to the memory went away, like an assignment or the end of a scope.
+    pub fn report_leaks(&mut self, function_end: bool, lost_at: Option<Span>) {
+        let mut leaks = Vec::new();
+        for heap in self.scopes[0].values() {
+            let Some(allocated_at) = heap.allocated_at else {
+                continue;
+            };
+            if !matches!(heap.var_type, VarType::Owner(_, true)) {
+                continue;
+            }
+            let pointed_to = heap.const_refs.iter().chain(&heap.mut_refs).any(|ref_id| {
+                self.is_in_scope(ref_id)
+                    && (!function_end || ref_id.scope == 0 || ref_id.name.contains('.'))
+                    && self
+                        .id_to_var(ref_id)
+                        .var_type
+                        .points_to()
+                        .is_some_and(|points_to| points_to.contains(&heap.id))
+            });
+            if !pointed_to {
+                // The name of the pointer the memory was allocated to, between the * and the @.
+                let owner = heap.id.name[1..heap.id.name.rfind('@').unwrap()].to_string();
+                leaks.push((owner, allocated_at));
+            }
+        }
+        leaks.sort_by_key(|(_, allocated_at)| allocated_at.start);
+        for (owner, allocated_at) in leaks {
+            self.report(
+                ErrorCode::MemoryLeak,
+                &allocated_at,
+                format!("Memory allocated to '{owner}' is never freed"),
+                Self::label(lost_at, "last pointer to it lost here"),
+                vec![owner],
+            );
+        }
+    }
+}
@@ -3,9 +3,48 @@ Rules:
     - Assigning to a variable makes it un-dead.
     - Using a variable alone on the RHS of an assignment or as an argument to a function call makes it dead.
     - Struct members are killed all together: 'struct.value.x'. If any piece 'struct.value' from left to right is dead, it is announced.
-    - If statements make copies of the dead variables state. At the end of the if/else, all the sets are unioned together.
+    - Moving a field out of a struct leaves the struct partially moved. Using the whole struct afterwards is an error that lists
+      the moved fields, until they are assigned again.
+    - p->x is (*p).x: p is checked like any other use of a reference, and the member is named after what p points to. Moving a
+      non-Copy member out from behind a pointer is an error.
+    - Function bodies are split into a control flow graph of basic blocks. The state at the start of a block is the union of the
+      states at the end of every block that leads to it (so at the end of an if/else, both branches' sets are unioned together).
+    - Blocks are rerun until every block's starting state stops changing (so loops are followed around), then each block is run
+      once more to report errors.
+    - return, break, continue and calls to noreturn functions (exit, abort, or anything declared _Noreturn) end their path. Their
+      state only reaches the function exit, the end of the loop, or the loop condition, never the next statement.
+    - Each switch case starts from the state before the switch joined with the case above it falling through. Without a default,
+      the state before the switch also reaches the end of the switch.
+    - A goto carries its state to its label, where it is joined with the state falling through to the label. Backward gotos
+      are followed around like loops.
     - Any use of a variable checks whether that variable has ownership (is not dead). If it is dead, an error is printed.
     - Any &x triggers a check to see if x already has a mutable reference. If it does, an error is printed.
+    - Struct fields are borrowed on their own: &s.x conflicts with borrows of s, s.x and anything under s.x, but not s.y.
+    - Enums are Copy. The members of a union share their memory, so writing one member invalidates every borrow of the others,
+      and reading a non-Copy member is a warning, since it may reinterpret a value written as another member.
+    - Owning pointers (from an allocator, __attribute__((annotate("owned"))) or --owned-names) are like Box. Assigning one or
+      passing it to an owning parameter moves it. Dereferencing it or passing it to any other parameter borrows through it.
+    - p = malloc(...) makes p own a new heap Owner. free(p) moves out of it, so using p afterwards or freeing it again
+      is an error. Heap memory that is still owned when nothing points to it anymore is reported as a leak (a warning).
+    - Returning a reference to a local variable or parameter (return &x, or return p where p points to one) is an error.
+      Returning an owner moves it to the caller.
+    - Annotations (the macros in include/ownership.h) override the VarType guessed from the declaration: owned, borrowed,
+      borrowed_mut, copy and nullable (a pointer that may point to nothing).
+    - Spec files (specs/libc.toml and any --spec) give the same kinds as annotations to functions and struct fields from
+      headers that cannot be changed. They override the prototypes and struct definitions.
+    - Lifetimes: a returned pointer borrows from the parameters with the same __attribute__((annotate("'a"))) lifetime as its
+      return type, or from the only pointer parameter if there are no lifetimes (Rust's elision rule). Bodies that return
+      references into other parameters are errors.
+    - Every function defined in the file is summarized before any are checked: which parameters it consumes (moves or frees)
+      and which arguments its returned pointer borrows from. A call to it only moves the consumed arguments, and its result
+      borrows from those arguments. Recursive functions are rechecked until their summaries stop changing.
+    - Calls to functions that were never declared follow --unknown-functions: by default every owner argument is moved and
+      every pointer argument is borrowed mutably. The arguments are always checked for moved values.
+    - An array is one variable for all of its elements. &arr[i], and passing arr to a function (it decays to a pointer),
+      borrow the whole array. Moving a non-Copy element out (x = arr[i]) is an error unless --allow-index-moves is given,
+      which moves the whole array instead.
+    - Pointer arithmetic keeps provenance: q = p + n, q = p - n and q = &p[i] borrow everything p points to, with the same
+      mutability, without invalidating p. Anything that invalidates p's borrow invalidates q's too.
 */
 
 /*
@@ -19,17 +58,35 @@ Limitations:
 
     - Rust places extra restrictions on globals, so I passed them in as function parameters instead.
     - Some unused struct fields that would require additional copy-pasting have been omitted. These have no effect on the output.
-    - Parser cannot parse <stdlib.h>, so tests with malloc and free are not possible.
+    - Parser cannot parse the system's <stdlib.h>, so the minimal headers in /include are used instead unless --system-headers
+      is given. They only declare the most common functions.
+    - The type of heap memory is not tracked, so moving out of it (x = *p) is always allowed.
 */
 
 #![feature(iter_intersperse)]
 
+mod array;
 mod ast_traversal;
 mod borrow_checker;
+mod cfg;
+mod compile_commands;
+mod dataflow;
+mod diagnostic;
+mod heap;
+mod options;
+mod output;
+mod pointer;
+mod spec;
+mod summary;
+mod union;
 mod variable;
 
 use borrow_checker::BorrowChecker;
 use borrow_checker::PrintType;
+use options::Options;
+use output::CheckedFile;
+use output::Format;
+use spec::Spec;
 
 use lang_c::driver::*;
 use lang_c::print::*;
@@ -38,30 +95,112 @@ use lang_c::visit::*;
 use std::io::Write;
 
 fn main() {
-    let file_path = "inputs\\kernel0\\round0.c";
-    let config = Config::default();
-    let result = parse(&config, file_path);
-
-    let parse = result.expect("Parsing Error!\n");
-
-    let mut borrow_checker = BorrowChecker::new(
-        vec!["perf_event_max_stack_handler".to_string()],
-        &parse.source,
-        false,
-        PrintType::ErrorOnly,
-        PrintType::ErrorOnly,
-    );
-
-    // Running the checker.
-    borrow_checker.visit_translation_unit(&parse.unit);
-    println!("\n\n"); // Spacing to make it easier to get images of the output.
-
-    // Printing the abstract syntax tree to a file.
-    let s = &mut String::new();
-    let mut printer = Printer::new(s);
-    printer.visit_translation_unit(&parse.unit);
-    let mut file = std::fs::File::create("ast.txt").expect("AST file creation failed");
-    file.write_all(s.as_bytes()).expect("AST file write failed");
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{}", options::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", options::USAGE);
+        return;
+    }
+
+    // Files named on the command line have no flags of their own.
+    let mut translation_units: Vec<(String, Vec<String>)> = options
+        .files
+        .iter()
+        .map(|file| (file.clone(), Vec::new()))
+        .collect();
+    if let Some(database) = &options.compile_commands {
+        match compile_commands::read(database) {
+            Ok(commands) => translation_units.extend(
+                commands
+                    .into_iter()
+                    .map(|command| (command.file, command.cpp_flags)),
+            ),
+            Err(message) => {
+                eprintln!("error: {message}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let spec = match Spec::read(&options.specs) {
+        Ok(spec) => spec,
+        Err(message) => {
+            eprintln!("error: {message}");
+            std::process::exit(2);
+        }
+    };
+
+    let stub_headers = match options::write_stub_headers() {
+        Ok(dir) => dir,
+        Err(message) => {
+            eprintln!("error: {message}");
+            std::process::exit(2);
+        }
+    };
+
+    let mut ast_output = String::new();
+    let mut errors_found = false;
+    let mut failed_files = Vec::new();
+    let mut checked_files = Vec::new();
+    for (file_path, file_flags) in &translation_units {
+        let config = options.config(file_flags, &stub_headers);
+
+        // A file that cannot be parsed is skipped, so the rest of the project is still checked.
+        let parse = match parse(&config, file_path) {
+            Ok(parse) => parse,
+            Err(error) => {
+                eprintln!("error: could not parse '{file_path}': {error}");
+                failed_files.push(file_path.clone());
+                continue;
+            }
+        };
+
+        let mut borrow_checker = BorrowChecker::new(options.checker_config(), &spec, &parse.source);
+
+        // Running the checker.
+        borrow_checker.visit_translation_unit(&parse.unit);
+        errors_found |= borrow_checker.has_errors();
+        let checked_file = CheckedFile {
+            path: file_path.clone(),
+            source: parse.source.clone(),
+            diagnostics: borrow_checker.diagnostics,
+        };
+        if let Format::Human = options.format {
+            output::print_human(&checked_file);
+            println!("\n\n"); // Spacing to make it easier to get images of the output.
+        }
+        checked_files.push(checked_file);
+
+        // Printing the abstract syntax tree.
+        if options.dump_ast.is_some() {
+            let mut printer = Printer::new(&mut ast_output);
+            printer.visit_translation_unit(&parse.unit);
+        }
+    }
+
+    if let Some(ast_path) = &options.dump_ast {
+        let mut file = std::fs::File::create(ast_path).expect("AST file creation failed");
+        file.write_all(ast_output.as_bytes())
+            .expect("AST file write failed");
+    }
+
+    match options.format {
+        Format::Human if translation_units.len() > 1 => {
+            output::print_summary(&checked_files, &failed_files)
+        }
+        Format::Human => {}
+        Format::Json => println!("{:#}", output::to_json(&checked_files)),
+        Format::Sarif => println!("{:#}", output::to_sarif(&checked_files)),
+    }
+
+    if errors_found || !failed_files.is_empty() {
+        std::process::exit(1);
+    }
 }
 
 // RUN                         cargo clippy            to view
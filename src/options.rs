@@ -0,0 +1,290 @@
+use crate::borrow_checker::CheckerConfig;
+use crate::borrow_checker::FunctionFilter;
+use crate::borrow_checker::UnknownFunctions;
+use crate::heap::HeapFunctions;
+use crate::output::Format;
+use crate::PrintType;
+use lang_c::driver::Config;
+use regex::Regex;
+
+// Minimal C library headers that lang_c can parse, used instead of the system ones. They are built into the binary,
+// like specs/libc.toml, and written to a temporary directory for the preprocessor.
+const STUB_HEADERS: [(&str, &str); 8] = [
+    ("assert.h", include_str!("../include/assert.h")),
+    ("ownership.h", include_str!("../include/ownership.h")),
+    ("stdbool.h", include_str!("../include/stdbool.h")),
+    ("stddef.h", include_str!("../include/stddef.h")),
+    ("stdint.h", include_str!("../include/stdint.h")),
+    ("stdio.h", include_str!("../include/stdio.h")),
+    ("stdlib.h", include_str!("../include/stdlib.h")),
+    ("string.h", include_str!("../include/string.h")),
+];
+
+// Writes the stub headers and returns the directory they are in. Each one is renamed into place, so checkers running
+// at the same time never read a partly written header.
+pub fn write_stub_headers() -> Result<String, String> {
+    let dir = std::env::temp_dir().join(concat!("c_borrow_checker-", env!("CARGO_PKG_VERSION")));
+    let error =
+        |e: std::io::Error| format!("cannot write the stub headers to '{}': {e}", dir.display());
+    std::fs::create_dir_all(&dir).map_err(error)?;
+    for (name, text) in STUB_HEADERS {
+        let partial = dir.join(format!("{name}.{}", std::process::id()));
+        std::fs::write(&partial, text).map_err(error)?;
+        std::fs::rename(&partial, dir.join(name)).map_err(error)?;
+    }
+    Ok(dir.display().to_string())
+}
+
+#[derive(Clone, Copy)]
+pub enum Preprocessor {
+    Gcc,
+    Clang,
+}
+
+pub const USAGE: &str = "Usage: c_borrow_checker [OPTIONS] <FILE>...
+
+Options:
+    --function <NAME>       Check the function with this name (repeatable).
+    --all-functions         Check every function definition in the file.
+    --include <REGEX>       Check every function whose name matches REGEX (repeatable).
+    --exclude <REGEX>       Skip every function whose name matches REGEX (repeatable).
+    --allocator <NAME>      Treat NAME like malloc: its result is new heap memory (repeatable).
+    --deallocator <NAME>    Treat NAME like free: it frees what its first argument points to (repeatable).
+    --reallocator <NAME>    Treat NAME like realloc: it frees its first argument and returns new memory (repeatable).
+    --owned-names <REGEX>   Treat pointer variables whose names match REGEX as owning what they point to.
+    --spec <PATH>           Read the ownership of functions and struct fields from a TOML spec (repeatable).
+    --unknown-functions <POLICY>
+                            What calls to undeclared functions do with their arguments: move owners and borrow
+                            pointers mutably, only borrow, or report the call (conservative|permissive|error).
+    --allow-index-moves     Moving a non-Copy element out of an array moves the whole array instead of being an error.
+    -I <DIR>                Search DIR for included headers (repeatable).
+    -D <NAME[=VALUE]>       Define a preprocessor macro (repeatable).
+    --preprocessor <CPP>    Preprocess with gcc or clang (gcc|clang).
+    --cpp-option <FLAG>     Pass FLAG to the preprocessor (repeatable).
+    --system-headers        Use the system's C library headers instead of the bundled stubs.
+    --compile-commands <PATH>
+                            Check every file in a compile_commands.json, each with its own include and macro flags.
+    --print-sets <KIND>     Print the ownership or reference sets on every line (ownership|reference|none).
+    --print-events          Print every ownership change, not only errors.
+    --print-globals         Include the global scope when printing sets.
+    --format <FORMAT>       How to print the diagnostics (human|json|sarif).
+    --dump-ast <PATH>       Write the abstract syntax tree of the input to PATH.
+    -h, --help              Print this message.";
+
+// Everything the user can control from the command line.
+pub struct Options {
+    pub files: Vec<String>,
+    pub functions: Vec<String>,
+    pub all_functions: bool,
+    pub include: Vec<Regex>,
+    pub exclude: Vec<Regex>,
+    // Heap functions in addition to the C library and Linux kernel ones.
+    pub allocators: Vec<String>,
+    pub deallocators: Vec<String>,
+    pub reallocators: Vec<String>,
+    pub owned_names: Option<Regex>,
+    // Spec files, read after the bundled one for the C library.
+    pub specs: Vec<String>,
+    pub unknown_functions: UnknownFunctions,
+    pub allow_index_moves: bool,
+    pub include_dirs: Vec<String>,
+    pub defines: Vec<String>,
+    pub preprocessor: Preprocessor,
+    pub cpp_options: Vec<String>,
+    pub system_headers: bool,
+    pub compile_commands: Option<String>,
+    pub set_prints: PrintType,
+    pub event_prints: PrintType,
+    pub print_globals: bool,
+    pub format: Format,
+    pub dump_ast: Option<String>,
+    pub help: bool,
+}
+
+impl Options {
+    // Reads the options from the program arguments (without the program name).
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            files: Vec::new(),
+            functions: Vec::new(),
+            all_functions: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            allocators: Vec::new(),
+            deallocators: Vec::new(),
+            reallocators: Vec::new(),
+            owned_names: None,
+            specs: Vec::new(),
+            unknown_functions: UnknownFunctions::Conservative,
+            allow_index_moves: false,
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+            preprocessor: Preprocessor::Gcc,
+            cpp_options: Vec::new(),
+            system_headers: false,
+            compile_commands: None,
+            set_prints: PrintType::ErrorOnly,
+            event_prints: PrintType::ErrorOnly,
+            print_globals: false,
+            format: Format::Human,
+            dump_ast: None,
+            help: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--function" => options.functions.push(Self::value(&arg, args.next())?),
+                "--all-functions" => options.all_functions = true,
+                "--include" => options.include.push(Self::regex(&arg, args.next())?),
+                "--exclude" => options.exclude.push(Self::regex(&arg, args.next())?),
+                "--allocator" => options.allocators.push(Self::value(&arg, args.next())?),
+                "--deallocator" => options.deallocators.push(Self::value(&arg, args.next())?),
+                "--reallocator" => options.reallocators.push(Self::value(&arg, args.next())?),
+                "--owned-names" => options.owned_names = Some(Self::regex(&arg, args.next())?),
+                "--spec" => options.specs.push(Self::value(&arg, args.next())?),
+                "--unknown-functions" => {
+                    options.unknown_functions = Self::policy(&arg, args.next())?
+                }
+                "--allow-index-moves" => options.allow_index_moves = true,
+                "--print-sets" => options.set_prints = Self::set_kind(&arg, args.next())?,
+                "-I" => options.include_dirs.push(Self::value(&arg, args.next())?),
+                "-D" => options.defines.push(Self::value(&arg, args.next())?),
+                "--preprocessor" => {
+                    options.preprocessor = match Self::value(&arg, args.next())?.as_str() {
+                        "gcc" => Preprocessor::Gcc,
+                        "clang" => Preprocessor::Clang,
+                        other => {
+                            return Err(format!(
+                                "'{other}' is not a preprocessor (expected gcc or clang)"
+                            ))
+                        }
+                    }
+                }
+                "--cpp-option" => options.cpp_options.push(Self::value(&arg, args.next())?),
+                "--system-headers" => options.system_headers = true,
+                "--compile-commands" => {
+                    options.compile_commands = Some(Self::value(&arg, args.next())?)
+                }
+                _ if arg.starts_with("-I") => options.include_dirs.push(arg[2..].to_string()),
+                _ if arg.starts_with("-D") => options.defines.push(arg[2..].to_string()),
+                "--print-events" => options.event_prints = PrintType::Ownership,
+                "--print-globals" => options.print_globals = true,
+                "--format" => {
+                    options.format = match Self::value(&arg, args.next())?.as_str() {
+                        "human" => Format::Human,
+                        "json" => Format::Json,
+                        "sarif" => Format::Sarif,
+                        other => {
+                            return Err(format!(
+                                "'{other}' is not an output format (expected human, json or sarif)"
+                            ))
+                        }
+                    }
+                }
+                "--dump-ast" => options.dump_ast = Some(Self::value(&arg, args.next())?),
+                "-h" | "--help" => options.help = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{arg}'")),
+                _ => options.files.push(arg),
+            }
+        }
+
+        if !options.help {
+            if options.files.is_empty() && options.compile_commands.is_none() {
+                return Err("no input files given".to_string());
+            }
+            if options.functions.is_empty() && !options.all_functions && options.include.is_empty()
+            {
+                return Err(
+                    "no functions to check (use --function, --all-functions or --include)"
+                        .to_string(),
+                );
+            }
+        }
+        Ok(options)
+    }
+
+    // How lang_c runs the preprocessor for a file with its own flags from compile_commands.json. User include
+    // directories are searched before the file's, and both before the stub headers in stub_headers. User macros
+    // override the file's.
+    pub fn config(&self, file_flags: &[String], stub_headers: &str) -> Config {
+        let mut config = match self.preprocessor {
+            Preprocessor::Gcc => Config::with_gcc(),
+            Preprocessor::Clang => Config::with_clang(),
+        };
+        for dir in &self.include_dirs {
+            config.cpp_options.push(format!("-I{dir}"));
+        }
+        config.cpp_options.extend(file_flags.iter().cloned());
+        if self.system_headers {
+            // Still finds ownership.h, after every system header.
+            config.cpp_options.push(format!("-idirafter{stub_headers}"));
+        } else {
+            config.cpp_options.push("-nostdinc".to_string());
+            config.cpp_options.push(format!("-I{stub_headers}"));
+        }
+        for define in &self.defines {
+            config.cpp_options.push(format!("-D{define}"));
+        }
+        config.cpp_options.extend(self.cpp_options.iter().cloned());
+        config
+    }
+
+    // How the checker runs, for every file.
+    pub fn checker_config(&self) -> CheckerConfig {
+        CheckerConfig {
+            to_check: FunctionFilter {
+                names: self.functions.clone(),
+                all: self.all_functions,
+                include: self.include.clone(),
+                exclude: self.exclude.clone(),
+            },
+            heap_functions: HeapFunctions::new(
+                &self.allocators,
+                &self.deallocators,
+                &self.reallocators,
+            ),
+            owned_pointer_names: self.owned_names.clone(),
+            unknown_functions: self.unknown_functions,
+            allow_index_moves: self.allow_index_moves,
+            print_global_scope_sets: self.print_globals,
+            set_prints: self.set_prints,
+            event_prints: self.event_prints,
+        }
+    }
+
+    // The value following an option that requires one.
+    fn value(option: &str, value: Option<String>) -> Result<String, String> {
+        value.ok_or_else(|| format!("option '{option}' requires a value"))
+    }
+
+    // The value following an option that requires a kind of set to print.
+    fn set_kind(option: &str, value: Option<String>) -> Result<PrintType, String> {
+        match Self::value(option, value)?.as_str() {
+            "ownership" => Ok(PrintType::Ownership),
+            "reference" => Ok(PrintType::Reference),
+            "none" => Ok(PrintType::ErrorOnly),
+            other => Err(format!(
+                "'{other}' is not a set kind (expected ownership, reference or none)"
+            )),
+        }
+    }
+
+    // The value following an option that requires a policy for unknown functions.
+    fn policy(option: &str, value: Option<String>) -> Result<UnknownFunctions, String> {
+        match Self::value(option, value)?.as_str() {
+            "conservative" => Ok(UnknownFunctions::Conservative),
+            "permissive" => Ok(UnknownFunctions::Permissive),
+            "error" => Ok(UnknownFunctions::Error),
+            other => Err(format!(
+                "'{other}' is not a policy (expected conservative, permissive or error)"
+            )),
+        }
+    }
+
+    // The value following an option that requires a regular expression.
+    fn regex(option: &str, value: Option<String>) -> Result<Regex, String> {
+        let pattern = Self::value(option, value)?;
+        Regex::new(&pattern).map_err(|e| format!("invalid pattern '{pattern}' for '{option}': {e}"))
+    }
+}
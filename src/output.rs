@@ -0,0 +1,166 @@
+use crate::diagnostic::*;
+use serde_json::json;
+use serde_json::Value;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Human,
+    Json,
+    Sarif,
+}
+
+// The results of checking one input file.
+pub struct CheckedFile {
+    pub path: String,
+    // The preprocessed source, which the diagnostic spans point into.
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CheckedFile {
+    // The file a location is in. Locations in the input file itself have no name if it was not preprocessed.
+    fn file_name<'a>(&'a self, location: &SourceLocation<'a>) -> &'a str {
+        if location.file.is_empty() {
+            &self.path
+        } else {
+            location.file
+        }
+    }
+}
+
+// Prints the diagnostics grouped under the name of the function they were found in.
+pub fn print_human(file: &CheckedFile) {
+    let mut function = None;
+    for diagnostic in &file.diagnostics {
+        if function != Some(&diagnostic.function) {
+            function = Some(&diagnostic.function);
+            println!("In function '{}':", diagnostic.function);
+        }
+        println!("{}", diagnostic.render(&file.source));
+    }
+}
+
+// The number of errors and warnings in each file, and the files that could not be parsed.
+pub fn print_summary(files: &[CheckedFile], failed_files: &[String]) {
+    println!("Summary:");
+    for file in files {
+        let count = |severity: Severity| {
+            file.diagnostics
+                .iter()
+                .filter(|d| d.severity == severity)
+                .count()
+        };
+        println!(
+            "    {}: {} errors, {} warnings",
+            file.path,
+            count(Severity::Error),
+            count(Severity::Warning)
+        );
+    }
+    for path in failed_files {
+        println!("    {path}: not checked, parsing failed");
+    }
+}
+
+// A flat list of every diagnostic in every file.
+pub fn to_json(files: &[CheckedFile]) -> Value {
+    let mut results = Vec::new();
+    for file in files {
+        for diagnostic in &file.diagnostics {
+            let location = diagnostic.location(&file.source);
+            let labels: Vec<Value> = diagnostic
+                .labels
+                .iter()
+                .map(|label| {
+                    let label_location = SourceLocation::new(&file.source, label.span.start);
+                    json!({
+                        "file": file.file_name(&label_location),
+                        "line": label_location.line,
+                        "column": label_location.column,
+                        "message": label.message,
+                    })
+                })
+                .collect();
+            results.push(json!({
+                "file": file.file_name(&location),
+                "line": location.line,
+                "column": location.column,
+                "function": diagnostic.function,
+                "code": diagnostic.code.code(),
+                "rule": diagnostic.code.rule_id(),
+                "severity": diagnostic.severity.name(),
+                "message": diagnostic.message,
+                "variables": diagnostic.variables,
+                "labels": labels,
+            }));
+        }
+    }
+    Value::Array(results)
+}
+
+// A SARIF 2.1.0 log with one run, for code-scanning viewers.
+pub fn to_sarif(files: &[CheckedFile]) -> Value {
+    let rules: Vec<Value> = ErrorCode::ALL
+        .iter()
+        .map(|code| {
+            json!({
+                "id": code.rule_id(),
+                "name": code.code(),
+                "shortDescription": { "text": code.description() },
+                "defaultConfiguration": { "level": code.severity().name() },
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for file in files {
+        for diagnostic in &file.diagnostics {
+            let location = diagnostic.location(&file.source);
+            let related_locations: Vec<Value> = diagnostic
+                .labels
+                .iter()
+                .map(|label| {
+                    let label_location = SourceLocation::new(&file.source, label.span.start);
+                    json!({
+                        "physicalLocation": physical_location(file, &label_location),
+                        "message": { "text": label.message },
+                    })
+                })
+                .collect();
+            results.push(json!({
+                "ruleId": diagnostic.code.rule_id(),
+                "ruleIndex": ErrorCode::ALL.iter().position(|c| *c == diagnostic.code),
+                "level": diagnostic.severity.name(),
+                "message": { "text": diagnostic.message },
+                "locations": [{
+                    "physicalLocation": physical_location(file, &location),
+                    "logicalLocations": [{ "name": diagnostic.function, "kind": "function" }],
+                }],
+                "relatedLocations": related_locations,
+            }));
+        }
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/DanielStebbins/c_borrow_checker",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn physical_location(file: &CheckedFile, location: &SourceLocation) -> Value {
+    json!({
+        "artifactLocation": { "uri": file.file_name(location) },
+        "region": { "startLine": location.line, "startColumn": location.column },
+    })
+}
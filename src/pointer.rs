@@ -0,0 +1,186 @@
+use crate::diagnostic::*;
+use crate::variable::*;
+use crate::BorrowChecker;
+use lang_c::ast::*;
+use lang_c::span::*;
+
+// Functions for pointer arithmetic. A pointer computed from another one, like p + 1, p - n or &p[i], points into the
+// same memory, so it borrows what the base pointer does.
+impl<'a> BorrowChecker<'a> {
+    // The pointer that an arithmetic expression starts from: p for p + n, n + p, p - n and &p[i].
+    pub fn arithmetic_base<'e>(
+        &mut self,
+        expression: &'e Node<Expression>,
+    ) -> Option<&'e Node<Expression>> {
+        match &expression.node {
+            Expression::BinaryOperator(boe)
+                if matches!(
+                    boe.node.operator.node,
+                    BinaryOperator::Plus | BinaryOperator::Minus
+                ) =>
+            {
+                if self.is_pointer_expression(&boe.node.lhs) {
+                    Some(&boe.node.lhs)
+                } else if boe.node.operator.node == BinaryOperator::Plus
+                    && self.is_pointer_expression(&boe.node.rhs)
+                {
+                    Some(&boe.node.rhs)
+                } else {
+                    None
+                }
+            }
+            // &arr[i] borrows the array itself, but &p[i] is p + i.
+            Expression::UnaryOperator(uoe) if uoe.node.operator.node == UnaryOperator::Address => {
+                match &uoe.node.operand.node {
+                    Expression::BinaryOperator(boe)
+                        if boe.node.operator.node == BinaryOperator::Index =>
+                    {
+                        let base = &boe.node.lhs;
+                        let is_array = self
+                            .expression_name(base)
+                            .is_some_and(|name| self.is_array(&name));
+                        (!is_array && self.is_pointer_expression(base)).then_some(base)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Whether an expression is a pointer (or an array, which decays to one), like p, s.p, (char *) p or p + 1.
+    pub fn is_pointer_expression(&mut self, expression: &Node<Expression>) -> bool {
+        match &expression.node {
+            Expression::Cast(cast_expression) => {
+                self.is_pointer_expression(&cast_expression.node.expression)
+            }
+            Expression::BinaryOperator(_) | Expression::UnaryOperator(_) => {
+                self.arithmetic_base(expression).is_some()
+            }
+            Expression::Identifier(_) | Expression::Member(_) => {
+                match self.expression_name(expression) {
+                    Some(name) if name != "NULL" => {
+                        let variable = self.name_to_var(&name);
+                        variable.array || variable.var_type.points_to().is_some()
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    // The pointer variable an expression is computed from: p for p, (char *) p, p + 1 and &p[i].
+    pub fn base_pointer_name(&mut self, expression: &Node<Expression>) -> Option<String> {
+        if let Some(base) = self.arithmetic_base(expression) {
+            return self.base_pointer_name(base);
+        }
+        match &expression.node {
+            Expression::Cast(cast_expression) => {
+                self.base_pointer_name(&cast_expression.node.expression)
+            }
+            Expression::Identifier(_) | Expression::Member(_) => self.expression_name(expression),
+            _ => None,
+        }
+    }
+
+    // q = p + n, where p is the base of the arithmetic. An array base decays, so q borrows the whole array.
+    pub fn add_derived_reference(&mut self, lhs: String, base: &Node<Expression>, span: &Span) {
+        if let Some(inner_base) = self.arithmetic_base(base) {
+            self.add_derived_reference(lhs, inner_base, span);
+            return;
+        }
+        match &base.node {
+            Expression::Cast(cast_expression) => {
+                self.add_derived_reference(lhs, &cast_expression.node.expression, span);
+            }
+            Expression::Identifier(_) | Expression::Member(_) => match self.expression_name(base) {
+                Some(name) if self.is_array(&name) => self.reference_from_address(lhs, base, span),
+                Some(name) => self.derive_pointer(lhs, name, span),
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    // q = p + n: q borrows everything p points to, mutably if p does, without taking the borrow away from p. Like a
+    // Rust reborrow, anything that invalidates p's borrow invalidates q's too.
+    pub fn derive_pointer(&mut self, lhs: String, base: String, span: &Span) {
+        let lhs_id = self.get_id(&lhs);
+        let base_id = self.get_id(&base);
+        let base_type = self.id_to_var(&base_id).var_type.clone();
+        let Some(targets) = base_type.points_to().cloned() else {
+            return;
+        };
+        // The targets are read before the LHS is cleared, since p = p + 1 derives from itself.
+        let mutable = !matches!(base_type, VarType::ConstRef(_));
+
+        self.clear_points_to(&lhs_id);
+        let lhs_var = self.id_to_mut_var(&lhs_id);
+        lhs_var.borrowed_at = Some(*span);
+        let lhs_mutable = match &mut lhs_var.var_type {
+            VarType::ConstRef(points_to) => {
+                points_to.extend(targets.clone());
+                false
+            }
+            VarType::MutRef(points_to) | VarType::OwnedPtr(points_to, _) => {
+                points_to.extend(targets.clone());
+                true
+            }
+            _ => return,
+        };
+
+        let in_scope_targets: Vec<Id> = targets
+            .into_iter()
+            .filter(|id| self.is_in_scope(id))
+            .collect();
+        for var_id in &in_scope_targets {
+            let var = self.id_to_mut_var(var_id);
+            if lhs_mutable {
+                var.mut_refs.insert(lhs_id.clone());
+            } else {
+                var.const_refs.insert(lhs_id.clone());
+            }
+        }
+        if lhs_mutable && !mutable {
+            self.report(
+                ErrorCode::RefKindMismatch,
+                span,
+                format!("Deriving mutable reference '{lhs}' from const reference '{base}'"),
+                vec![],
+                vec![base, lhs],
+            );
+        }
+    }
+
+    // Whether a member is reached through a pointer, like p->x, (*p).x or p->x.y.
+    pub fn is_behind_pointer(expression: &Node<Expression>) -> bool {
+        match &expression.node {
+            Expression::Member(member_expression) => {
+                member_expression.node.operator.node == MemberOperator::Indirect
+                    || Self::is_behind_pointer(&member_expression.node.expression)
+            }
+            Expression::UnaryOperator(uoe) => uoe.node.operator.node == UnaryOperator::Indirection,
+            _ => false,
+        }
+    }
+
+    // x = p->y: a non-Copy field cannot be moved out from behind a pointer, unless the pointer owns heap memory,
+    // whose type is not tracked.
+    pub fn move_out_of_pointer(&mut self, name: String, span: &Span) {
+        let root = self.root_variable(&name).unwrap_or_default();
+        let on_heap = self.name_to_var(&root).allocated_at.is_some();
+        match self.name_to_var(&name).var_type {
+            VarType::Owner(_, _) | VarType::OwnedPtr(_, _) if !on_heap => {
+                self.report(
+                    ErrorCode::MoveBehindReference,
+                    span,
+                    format!("Cannot move non-Copy type '{name}' from behind a reference"),
+                    vec![],
+                    vec![name],
+                );
+            }
+            _ => self.set_all_ownership(name, false, span),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::variable::*;
+use crate::BorrowChecker;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use toml::Table;
+use toml::Value;
+
+// The spec for the C library, which is always loaded before the ones given with --spec.
+const LIBC_SPEC: &str = include_str!("../specs/libc.toml");
+
+// Kinds are named like the annotations in include/ownership.h. A freed parameter is freed by the function, like the
+// argument of free.
+const PARAMETER_KINDS: [&str; 5] = ["copy", "owned", "borrowed", "borrowed_mut", "freed"];
+const FIELD_KINDS: [&str; 5] = ["copy", "owned", "borrowed", "borrowed_mut", "nullable"];
+
+// What the result of a function is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Returns {
+    // New heap memory that the caller owns, like the result of malloc.
+    Owned,
+    // A pointer into the arguments at these indices. With none of them, it points to something global.
+    From(Vec<usize>),
+}
+
+pub struct FunctionSpec {
+    pub parameters: Vec<String>,
+    pub returns: Option<Returns>,
+}
+
+// The ownership of functions and structs declared in headers that cannot be annotated, like the C library or the
+// kernel, read from TOML files.
+#[derive(Default)]
+pub struct Spec {
+    pub functions: HashMap<String, FunctionSpec>,
+    // The kinds of the fields of each struct, for the fields that have one.
+    pub structs: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+impl Spec {
+    // The bundled C library spec, then every spec file in order. A later spec replaces the functions and fields of an
+    // earlier one.
+    pub fn read(paths: &[String]) -> Result<Spec, String> {
+        let mut spec = Spec::default();
+        spec.add("specs/libc.toml", LIBC_SPEC)?;
+        for path in paths {
+            let text =
+                std::fs::read_to_string(path).map_err(|e| format!("cannot read '{path}': {e}"))?;
+            spec.add(path, &text)?;
+        }
+        Ok(spec)
+    }
+
+    fn add(&mut self, path: &str, text: &str) -> Result<(), String> {
+        let table: Table = text
+            .parse()
+            .map_err(|e| format!("'{path}' is not valid TOML: {e}"))?;
+        for (section, value) in &table {
+            match (section.as_str(), value) {
+                ("functions", Value::Table(functions)) => {
+                    for (name, function) in functions {
+                        let function = Self::function(path, name, function)?;
+                        self.functions.insert(name.clone(), function);
+                    }
+                }
+                ("structs", Value::Table(structs)) => {
+                    for (name, fields) in structs {
+                        let Value::Table(fields) = fields else {
+                            return Err(format!("the struct '{name}' in '{path}' is not a table"));
+                        };
+                        let struct_fields = self.structs.entry(name.clone()).or_default();
+                        for (field, kinds) in fields {
+                            let kinds = kinds
+                                .as_str()
+                                .map(|kinds| kinds.split_whitespace().map(str::to_string).collect())
+                                .filter(|kinds: &Vec<String>| {
+                                    kinds.iter().all(|k| FIELD_KINDS.contains(&k.as_str()))
+                                })
+                                .ok_or_else(|| {
+                                    format!("the field '{name}.{field}' in '{path}' has an unknown kind {kinds}")
+                                })?;
+                            struct_fields.insert(field.clone(), kinds);
+                        }
+                    }
+                }
+                _ => return Err(format!("'{path}' has an unknown section '{section}'")),
+            }
+        }
+        Ok(())
+    }
+
+    // One function, like { parameters = ["borrowed_mut", "borrowed"], returns_from = [0] }.
+    fn function(path: &str, name: &str, value: &Value) -> Result<FunctionSpec, String> {
+        let error = |message: String| format!("the function '{name}' in '{path}' {message}");
+        let Value::Table(table) = value else {
+            return Err(error("is not a table".to_string()));
+        };
+
+        let mut function = FunctionSpec {
+            parameters: Vec::new(),
+            returns: None,
+        };
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("parameters", Value::Array(kinds)) => {
+                    for (index, kind) in kinds.iter().enumerate() {
+                        let Some(kind) = kind.as_str().filter(|k| PARAMETER_KINDS.contains(k))
+                        else {
+                            return Err(error(format!("has a parameter of unknown kind {kind}")));
+                        };
+                        // Like free, only the first argument can be freed.
+                        if kind == "freed" && index != 0 {
+                            return Err(error(
+                                "frees a parameter other than the first".to_string(),
+                            ));
+                        }
+                        function.parameters.push(kind.to_string());
+                    }
+                }
+                ("returns", Value::String(kind)) => {
+                    function.returns = Some(match kind.as_str() {
+                        "owned" => Returns::Owned,
+                        "copy" => Returns::From(Vec::new()),
+                        other => return Err(error(format!("returns unknown kind '{other}'"))),
+                    })
+                }
+                ("returns_from", Value::Array(indices)) => {
+                    let indices = indices
+                        .iter()
+                        .map(|i| i.as_integer().and_then(|i| usize::try_from(i).ok()))
+                        .collect::<Option<Vec<usize>>>()
+                        .ok_or_else(|| {
+                            error("has a returns_from that is not a list of indices".to_string())
+                        })?;
+                    function.returns = Some(Returns::From(indices));
+                }
+                _ => return Err(error(format!("has an unknown or invalid key '{key}'"))),
+            }
+        }
+
+        if let Some(Returns::From(indices)) = &function.returns {
+            if indices.iter().any(|i| *i >= function.parameters.len()) {
+                return Err(error(
+                    "returns from a parameter it does not have".to_string(),
+                ));
+            }
+        }
+        Ok(function)
+    }
+}
+
+// Functions for using the specs, which act like annotations written outside the headers.
+impl<'a> BorrowChecker<'a> {
+    // Adds every function in the spec before the traversal, so functions without a prototype are known. Until the
+    // prototype is seen, every parameter that is not copy is assumed to be a pointer.
+    pub fn add_function_specs(&mut self) {
+        let spec = self.spec;
+        for (name, function) in &spec.functions {
+            let parameters = function
+                .parameters
+                .iter()
+                .map(|kind| Self::parameter_type(VarType::MutRef(HashSet::new()), kind))
+                .collect();
+            self.functions.insert(name.clone(), parameters);
+
+            let frees = function.parameters.first().is_some_and(|k| k == "freed");
+            match &function.returns {
+                Some(Returns::Owned) if frees => {
+                    self.heap_functions.reallocators.insert(name.clone())
+                }
+                Some(Returns::Owned) => self.heap_functions.allocators.insert(name.clone()),
+                _ if frees => self.heap_functions.deallocators.insert(name.clone()),
+                _ => false,
+            };
+            if let Some(Returns::From(indices)) = &function.returns {
+                self.lifetimes.insert(name.clone(), indices.clone());
+            }
+        }
+    }
+
+    // The type of a parameter from its prototype, changed by its kind in the spec. Freed parameters own what they
+    // point to.
+    pub fn parameter_type(var_type: VarType, kind: &str) -> VarType {
+        match kind {
+            "freed" => Self::annotated_type(var_type, "owned"),
+            _ => Self::annotated_type(var_type, kind),
+        }
+    }
+
+    // Changes the types of a struct's fields to the kinds the spec gives them, and records the nullable ones.
+    pub fn apply_struct_spec(
+        &self,
+        struct_names: &HashSet<String>,
+        struct_members: &mut HashMap<String, VarType>,
+        nullable_members: &mut Vec<String>,
+    ) {
+        let Some(fields) = struct_names
+            .iter()
+            .find_map(|name| self.spec.structs.get(name))
+        else {
+            return;
+        };
+        for (field, kinds) in fields {
+            for kind in kinds {
+                if kind == "nullable" {
+                    nullable_members.push(field.clone());
+                } else if let Some(var_type) = struct_members.get_mut(field) {
+                    *var_type = Self::annotated_type(var_type.clone(), kind);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use crate::borrow_checker::UnknownFunctions;
+use crate::diagnostic::ErrorCode;
+use crate::variable::*;
+use crate::BorrowChecker;
+use lang_c::ast::*;
+use lang_c::span::*;
+use lang_c::visit::Visit;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+// Recursive functions whose summaries still change after this many rounds keep the last one.
+const MAX_SUMMARY_ROUNDS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterMode {
+    // The function only uses the argument, so the caller still owns it afterwards.
+    Borrowed,
+    // The function moves the argument or frees what it points to, so the caller loses it.
+    Consumed,
+}
+
+// What a function does with its arguments and what its result points to, as seen by its callers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionSummary {
+    pub parameters: Vec<ParameterMode>,
+    // The arguments that a returned pointer borrows from.
+    pub returns_from: Vec<usize>,
+    // Whether the result is new heap memory that the caller owns, like the result of malloc.
+    pub returns_allocation: bool,
+}
+
+// Functions for summarizing function definitions and using the summaries at calls.
+impl<'a> BorrowChecker<'a> {
+    // Finds the summary of every function defined in the translation unit that a function being checked can call.
+    // Every body is checked silently with the summaries from the previous round, starting from summaries that consume
+    // nothing, until none of them change. A function whose check runs into an internal issue gets no summary, so
+    // calls to it are checked conservatively from its signature alone.
+    pub fn summarize_functions(&mut self, translation_unit: &TranslationUnit) {
+        let silent = self.silent;
+        self.silent = true;
+
+        // Declarations are visited in order, so every struct and function is known before any body is checked.
+        let mut definitions = Vec::new();
+        for external_declaration in &translation_unit.0 {
+            match &external_declaration.node {
+                ExternalDeclaration::FunctionDefinition(function_definition) => {
+                    let function_definition = &function_definition.node;
+                    self.add_function_definition(function_definition);
+                    self.add_if_noreturn(
+                        &function_definition.declarator,
+                        &function_definition.specifiers,
+                    );
+                    definitions.push(function_definition);
+                }
+                _ => self.visit_external_declaration(
+                    &external_declaration.node,
+                    &external_declaration.span,
+                ),
+            }
+        }
+        let definitions = self.reachable_definitions(definitions);
+        for function_definition in &definitions {
+            let name = Self::function_name(function_definition);
+            let parameter_count = self.functions.get(&name).map_or(0, |p| p.len());
+            self.summaries.insert(
+                name,
+                FunctionSummary {
+                    parameters: vec![ParameterMode::Borrowed; parameter_count],
+                    returns_from: Vec::new(),
+                    returns_allocation: false,
+                },
+            );
+        }
+
+        let mut failed = HashSet::new();
+        for _ in 0..MAX_SUMMARY_ROUNDS {
+            let mut changed = false;
+            for function_definition in &definitions {
+                self.current_function = Self::function_name(function_definition);
+                if failed.contains(&self.current_function) {
+                    continue;
+                }
+                let issues = self.issues;
+                let global_scope = self.scopes[0].clone();
+                self.check_function(function_definition);
+                let summary = self.summary(function_definition);
+                self.scopes = vec![global_scope];
+
+                if self.issues > issues {
+                    self.summaries.remove(&self.current_function);
+                    failed.insert(self.current_function.clone());
+                    changed = true;
+                } else if self.summaries.get(&self.current_function) != Some(&summary) {
+                    self.summaries
+                        .insert(self.current_function.clone(), summary);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // The real check sees every declaration again, starting from nothing. Only the functions are kept, so calls
+        // to functions defined later in the file are known.
+        self.current_function.clear();
+        self.scopes = vec![HashMap::new()];
+        self.structs.clear();
+        self.silent = silent;
+    }
+
+    // The definitions of the functions being checked and of every function they call, directly or not.
+    fn reachable_definitions<'t>(
+        &self,
+        definitions: Vec<&'t FunctionDefinition>,
+    ) -> Vec<&'t FunctionDefinition> {
+        let calls: HashMap<String, HashSet<String>> = definitions
+            .iter()
+            .map(|function_definition| {
+                let mut callees = Callees(HashSet::new());
+                callees.visit_statement(
+                    &function_definition.statement.node,
+                    &function_definition.statement.span,
+                );
+                (Self::function_name(function_definition), callees.0)
+            })
+            .collect();
+        let mut reachable: HashSet<String> = calls
+            .keys()
+            .filter(|name| self.functions_to_check.matches(name))
+            .cloned()
+            .collect();
+        let mut worklist: Vec<String> = reachable.iter().cloned().collect();
+        while let Some(name) = worklist.pop() {
+            for callee in calls.get(&name).into_iter().flatten() {
+                if reachable.insert(callee.clone()) {
+                    worklist.push(callee.clone());
+                }
+            }
+        }
+        definitions
+            .into_iter()
+            .filter(|function_definition| {
+                reachable.contains(&Self::function_name(function_definition))
+            })
+            .collect()
+    }
+
+    // Records the parameter types of a function that might not have a prototype. f() has no parameter types.
+    fn add_function_definition(&mut self, function_definition: &FunctionDefinition) {
+        for derived_declarator in &function_definition.declarator.node.derived {
+            match &derived_declarator.node {
+                DerivedDeclarator::Function(function_declarator) => self.add_function(
+                    &function_definition.declarator,
+                    &function_declarator.node.parameters,
+                    &function_definition.specifiers,
+                ),
+                DerivedDeclarator::KRFunction(_) => self.add_function(
+                    &function_definition.declarator,
+                    &Vec::new(),
+                    &function_definition.specifiers,
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    fn function_name(function_definition: &FunctionDefinition) -> String {
+        match &function_definition.declarator.node.kind.node {
+            DeclaratorKind::Identifier(id) => id.node.name.clone(),
+            _ => String::new(),
+        }
+    }
+
+    // The names of a function's parameters, in the same order as in self.functions. Unnamed ones have no name.
+    pub fn parameter_names(function_definition: &FunctionDefinition) -> Vec<Option<String>> {
+        let mut names = Vec::new();
+        for derived_declarator in &function_definition.declarator.node.derived {
+            let DerivedDeclarator::Function(function_declarator) = &derived_declarator.node else {
+                continue;
+            };
+            for parameter in &function_declarator.node.parameters {
+                let Some(declarator) = &parameter.node.declarator else {
+                    continue;
+                };
+                names.push(match &declarator.node.kind.node {
+                    DeclaratorKind::Identifier(id) => Some(id.node.name.clone()),
+                    _ => None,
+                });
+            }
+        }
+        names
+    }
+
+    // The summary of the function that was just checked, from the state at its end.
+    fn summary(&self, function_definition: &FunctionDefinition) -> FunctionSummary {
+        let mut summary = FunctionSummary {
+            parameters: Vec::new(),
+            returns_from: Vec::new(),
+            returns_allocation: false,
+        };
+        for (index, name) in Self::parameter_names(function_definition)
+            .into_iter()
+            .enumerate()
+        {
+            let Some(name) = name else {
+                summary.parameters.push(ParameterMode::Borrowed);
+                continue;
+            };
+
+            // An owner parameter is consumed if it or any of its members were moved. A pointer parameter is
+            // consumed if what it points to (the ?name unknown global) was freed or given away.
+            let is_piece = |variable_name: &str, root: &str| {
+                variable_name == root || variable_name.starts_with(&format!("{root}."))
+            };
+            let moved = self.scopes.get(1).is_some_and(|scope| {
+                scope
+                    .values()
+                    .any(|v| is_piece(&v.id.name, &name) && v.killed_at.is_some())
+            });
+            let unknown_name = format!("?{name}");
+            let freed = self.scopes[0]
+                .values()
+                .any(|v| is_piece(&v.id.name, &unknown_name) && v.killed_at.is_some());
+            summary.parameters.push(if moved || freed {
+                ParameterMode::Consumed
+            } else {
+                ParameterMode::Borrowed
+            });
+
+            if self
+                .return_points_to
+                .iter()
+                .any(|id| is_piece(&id.name, &unknown_name))
+            {
+                summary.returns_from.push(index);
+            }
+        }
+        summary.returns_allocation = self.return_points_to.iter().any(|id| {
+            self.scopes[0]
+                .get(&id.name)
+                .is_some_and(|v| v.allocated_at.is_some())
+        });
+        summary
+    }
+
+    // The parameter types and summary used for a call to a function that was never declared, from the policy for
+    // unknown functions. Permissive calls borrow everything, like a function whose summary consumes nothing.
+    pub fn unknown_function(
+        &mut self,
+        function_name: Option<&str>,
+        argument_count: usize,
+        span: &Span,
+    ) -> (Vec<VarType>, Option<FunctionSummary>) {
+        let borrow_only = (
+            vec![VarType::ConstRef(HashSet::new()); argument_count],
+            Some(FunctionSummary {
+                parameters: vec![ParameterMode::Borrowed; argument_count],
+                returns_from: Vec::new(),
+                returns_allocation: false,
+            }),
+        );
+        match self.unknown_functions {
+            UnknownFunctions::Conservative => {
+                (vec![VarType::MutRef(HashSet::new()); argument_count], None)
+            }
+            UnknownFunctions::Permissive => borrow_only,
+            UnknownFunctions::Error => {
+                if let Some(function_name) = function_name {
+                    self.report(
+                        ErrorCode::UnknownFunction,
+                        span,
+                        format!("Call to '{function_name}', which was never declared"),
+                        vec![],
+                        vec![function_name.to_string()],
+                    );
+                }
+                borrow_only
+            }
+        }
+    }
+
+    // The argument of a call that a function consumes: anything it points to is given away, and an owning pointer
+    // is moved.
+    pub fn consume(&mut self, argument: &Node<Expression>, span: &Span) {
+        let Some(name) = self.expression_name(argument) else {
+            return;
+        };
+        for target in self.in_scope_targets(&name) {
+            let variable = self.id_to_mut_var(&target);
+            if let VarType::Owner(type_name, true) = variable.var_type.clone() {
+                variable.var_type = VarType::Owner(type_name, false);
+            }
+            variable.killed_at = Some(*span);
+        }
+    }
+
+    // p = f(&x, q): p borrows from the arguments that the signature of f says its result points into, or if it does
+    // not say, that the summary of f does.
+    pub fn borrow_from_arguments(
+        &mut self,
+        lhs: &str,
+        call_expression: &Node<CallExpression>,
+        span: &Span,
+    ) {
+        let Expression::Identifier(function_id) = &call_expression.node.callee.node else {
+            return;
+        };
+        let function_name = &function_id.node.name;
+        self.annotate_result(lhs, function_name);
+        let returns_from = match (
+            self.lifetimes.get(function_name),
+            self.summaries.get(function_name),
+        ) {
+            (Some(returns_from), _) => returns_from.clone(),
+            (None, Some(summary)) => summary.returns_from.clone(),
+            (None, None) => return,
+        };
+        let lhs_id = self.get_id(lhs);
+        if self.name_to_var(lhs).var_type.points_to().is_none() {
+            return;
+        }
+
+        let mut targets = Vec::new();
+        if returns_from.is_empty() {
+            if !self.lifetimes.contains_key(function_name) {
+                return;
+            }
+            // A 'static result points to something global that the checker knows nothing about, like ?p for a
+            // pointer parameter p.
+            let unknown_name = format!("?{function_name}()");
+            self.scopes[0]
+                .entry(unknown_name.clone())
+                .or_insert_with(|| Variable::new(unknown_name.clone(), 0, VarType::Copy));
+            targets.push(Id {
+                name: unknown_name,
+                scope: 0,
+            });
+        }
+        for index in returns_from {
+            let Some(argument) = call_expression.node.arguments.get(index) else {
+                continue;
+            };
+            match &argument.node {
+                Expression::UnaryOperator(uoe)
+                    if uoe.node.operator.node == UnaryOperator::Address =>
+                {
+                    targets.extend(self.borrowed_variable(&uoe.node.operand));
+                }
+                _ => {
+                    if let Some(name) = self.expression_name(argument) {
+                        targets.extend(self.in_scope_targets(&name));
+                    }
+                }
+            }
+        }
+
+        // The result is a new reference to the same variables, without invalidating the arguments.
+        self.clear_points_to(&lhs_id);
+        let reference = self.id_to_mut_var(&lhs_id);
+        reference.borrowed_at = Some(*span);
+        let is_const = matches!(reference.var_type, VarType::ConstRef(_));
+        if let Some(points_to) = reference.var_type.points_to_mut() {
+            points_to.extend(targets.iter().cloned());
+        }
+        for target in targets
+            .iter()
+            .filter(|id| self.is_in_scope(id))
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            let variable = self.id_to_mut_var(&target);
+            if is_const {
+                variable.const_refs.insert(lhs_id.clone());
+            } else {
+                variable.mut_refs.insert(lhs_id.clone());
+            }
+        }
+    }
+
+    // p = f(...) for f annotated with the kind of its result: p becomes that kind of reference, like a Rust variable
+    // takes the type of what initializes it, and may point to nothing if the result is nullable.
+    fn annotate_result(&mut self, lhs: &str, function_name: &str) {
+        let Some(annotations) = self.result_annotations.get(function_name).cloned() else {
+            return;
+        };
+        let lhs_id = self.get_id(lhs);
+        if !matches!(
+            self.id_to_var(&lhs_id).var_type,
+            VarType::ConstRef(_) | VarType::MutRef(_)
+        ) {
+            return;
+        }
+        if annotations.iter().any(|a| a == "nullable") {
+            self.id_to_mut_var(&lhs_id).nullable = true;
+        }
+        let Some(kind) = annotations
+            .iter()
+            .find(|a| *a == "borrowed" || *a == "borrowed_mut")
+        else {
+            return;
+        };
+        self.clear_points_to(&lhs_id);
+        let variable = self.id_to_mut_var(&lhs_id);
+        variable.var_type = Self::annotated_type(variable.var_type.clone(), kind);
+    }
+}
+
+// The names of the functions called in a function body.
+struct Callees(HashSet<String>);
+
+impl<'ast> Visit<'ast> for Callees {
+    fn visit_call_expression(&mut self, call_expression: &'ast CallExpression, span: &'ast Span) {
+        if let Expression::Identifier(id) = &call_expression.callee.node {
+            self.0.insert(id.node.name.clone());
+        }
+        lang_c::visit::visit_call_expression(self, call_expression, span);
+    }
+}
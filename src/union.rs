@@ -0,0 +1,87 @@
+use crate::diagnostic::*;
+use crate::variable::*;
+use crate::BorrowChecker;
+use lang_c::span::*;
+
+// Functions for unions. Every member of a union shares the same memory, so writing one member overwrites the others,
+// and a value read from one member may have been written as another.
+impl<'a> BorrowChecker<'a> {
+    // The union a member belongs to: u for u.x, if u is a union.
+    pub fn union_of(&mut self, name: &str) -> Option<(String, String)> {
+        let dot = name.rfind('.')?;
+        match self.name_to_var(&name[..dot]).var_type.clone() {
+            VarType::Owner(type_name, _) if self.unions.contains(&type_name) => {
+                Some((name[..dot].to_string(), type_name))
+            }
+            _ => None,
+        }
+    }
+
+    // The union members a name is part of, from the outermost: u.x for u.x.y, and u.x and u.x.v.z for u.x.v.z.
+    fn union_members(&mut self, name: &str) -> Vec<String> {
+        let mut members = Vec::new();
+        for (index, _) in name.match_indices('.') {
+            let next_dot = name[index + 1..]
+                .find('.')
+                .map_or(name.len(), |i| index + 1 + i);
+            let member = &name[..next_dot];
+            if self.root_variable(&name[..index]).is_some() && self.union_of(member).is_some() {
+                members.push(member.to_string());
+            }
+        }
+        members
+    }
+
+    // u.x = ...: the other members of u now hold a reinterpretation of x, so every borrow of them is invalidated.
+    pub fn overwrite_union_members(&mut self, name: &str) {
+        for member in self.union_members(name) {
+            let Some((union_name, type_name)) = self.union_of(&member) else {
+                continue;
+            };
+            let fields: Vec<String> = self
+                .structs
+                .get(&type_name)
+                .map(|fields| fields.keys().cloned().collect())
+                .unwrap_or_default();
+            for field in fields {
+                let other = format!("{union_name}.{field}");
+                if other == member {
+                    continue;
+                }
+                let scope = self.get_scope_number(&other);
+                let pieces: Vec<String> = self.scopes[scope]
+                    .keys()
+                    .filter(|k| *k == &other || k.starts_with(&format!("{other}.")))
+                    .cloned()
+                    .collect();
+                for piece in pieces {
+                    let variable = self.name_to_mut_var(&piece);
+                    variable.const_refs.clear();
+                    variable.mut_refs.clear();
+                }
+            }
+        }
+    }
+
+    // x = u.y: reading a non-Copy member of a union is like Rust's unsafe union read, because the bytes may have been
+    // written as a different member, so the owner may not be valid.
+    pub fn read_union_member(&mut self, name: &str, span: &Span) {
+        for member in self.union_members(name) {
+            if !matches!(
+                self.name_to_var(&member).var_type,
+                VarType::Owner(_, _) | VarType::OwnedPtr(_, _)
+            ) {
+                continue;
+            }
+            let (union_name, _) = self.union_of(&member).unwrap_or_default();
+            self.report(
+                ErrorCode::UnionReinterpretation,
+                span,
+                format!("Reading '{member}', a non-Copy member of union '{union_name}', may reinterpret another member"),
+                vec![],
+                vec![member.clone(), union_name],
+            );
+            return;
+        }
+    }
+}
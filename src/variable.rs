@@ -1,11 +1,35 @@
+use lang_c::span::Span;
 use std::collections::HashSet;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VarType {
     Copy,
     Owner(String, bool),
     ConstRef(HashSet<Id>),
     MutRef(HashSet<Id>),
+    // A pointer that owns what it points to, like a Box. It borrows its target mutably, and can itself be moved.
+    OwnedPtr(HashSet<Id>, bool),
+}
+
+impl VarType {
+    // The variables a pointer might point to.
+    pub fn points_to(&self) -> Option<&HashSet<Id>> {
+        match self {
+            VarType::ConstRef(points_to)
+            | VarType::MutRef(points_to)
+            | VarType::OwnedPtr(points_to, _) => Some(points_to),
+            _ => None,
+        }
+    }
+
+    pub fn points_to_mut(&mut self) -> Option<&mut HashSet<Id>> {
+        match self {
+            VarType::ConstRef(points_to)
+            | VarType::MutRef(points_to)
+            | VarType::OwnedPtr(points_to, _) => Some(points_to),
+            _ => None,
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
@@ -19,6 +43,25 @@ pub struct Variable {
     pub var_type: VarType,
     pub const_refs: HashSet<Id>,
     pub mut_refs: HashSet<Id>,
+
+    // Where the variable last lost ownership, and where its current borrow was created (for diagnostics).
+    pub killed_at: Option<Span>,
+    pub borrowed_at: Option<Span>,
+
+    // For heap memory, where it was allocated and where it was freed.
+    pub allocated_at: Option<Span>,
+    pub freed_at: Option<Span>,
+
+    // Pointers annotated nullable may point to nothing.
+    pub nullable: bool,
+
+    // Arrays are one variable for all of their elements, so assigning to one element does not overwrite the others.
+    pub array: bool,
+    // The number of dimensions of an array, like 2 for int m[2][3], whose elements m[i] are arrays too.
+    pub dimensions: usize,
+
+    // Static locals live for the whole program, like globals, even though only their function can name them.
+    pub is_static: bool,
 }
 
 impl Variable {
@@ -28,6 +71,14 @@ impl Variable {
             var_type: var_type,
             const_refs: HashSet::new(),
             mut_refs: HashSet::new(),
+            killed_at: None,
+            borrowed_at: None,
+            allocated_at: None,
+            freed_at: None,
+            nullable: false,
+            array: false,
+            dimensions: 0,
+            is_static: false,
         }
     }
 }
@@ -39,6 +90,24 @@ impl Clone for Variable {
             var_type: self.var_type.clone(),
             const_refs: self.const_refs.clone(),
             mut_refs: self.mut_refs.clone(),
+            killed_at: self.killed_at,
+            borrowed_at: self.borrowed_at,
+            allocated_at: self.allocated_at,
+            freed_at: self.freed_at,
+            nullable: self.nullable,
+            array: self.array,
+            dimensions: self.dimensions,
+            is_static: self.is_static,
         }
     }
 }
+
+// Two variables are in the same state if they have the same ownership and references. Where they were killed or borrowed does not matter.
+impl PartialEq for Variable {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.var_type == other.var_type
+            && self.const_refs == other.const_refs
+            && self.mut_refs == other.mut_refs
+    }
+}